@@ -2,4 +2,4 @@
 ssh pi@hourglass "mkdir ~/html" \
 && ssh pi@hourglass "mkdir ~/audio" \
 && scp html/index.html pi@hourglass:~/html \
-&& scp audio/424244__aceinet__number-90-flange-the-hammer-on-e.wav pi@hourglass:~/audio
\ No newline at end of file
+&& scp audio/424244__aceinet__number-90-flange-the-hammer-on-e.wav pi@hourglass:~/audio/alarm.wav
\ No newline at end of file
@@ -0,0 +1,511 @@
+use std::time::Duration;
+
+use crate::audio::sound_bank::SoundId;
+use crate::audio::{PlayOptions, Player};
+use crate::gui::display_control::{DisplayControl, DEFAULT_CONTRAST};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_ALARM_PROFILE: &str = "default";
+const MIN_PULSE_CONTRAST: u8 = 0x08;
+const MAX_CONTRAST: u8 = 0xFF;
+// Contrast levels of a pulse, the loop only wakes for these.
+const PULSE_STEPS: u128 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlarmPattern {
+    /// Lit for the whole alarm.
+    Steady,
+    /// Alternates between lit and dark every interval_ms.
+    Blink,
+    /// Lit for the whole alarm, the contrast fades down and up every interval_ms.
+    Pulse,
+}
+
+impl AlarmPattern {
+    pub const ALL: [AlarmPattern; 3] = [
+        AlarmPattern::Steady,
+        AlarmPattern::Blink,
+        AlarmPattern::Pulse,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlarmPattern::Steady => "steady",
+            AlarmPattern::Blink => "blink",
+            AlarmPattern::Pulse => "pulse",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AlarmPattern> {
+        AlarmPattern::ALL
+            .iter()
+            .copied()
+            .find(|pattern| pattern.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmVisual {
+    pub pattern: AlarmPattern,
+    pub interval_ms: u128,
+    /// Swaps lit and dark, e.g. for white text on black while lit.
+    pub inverted: bool,
+    /// Text like "time's up", each word on its own line.
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AlarmSound {
+    pub sound: SoundId,
+    pub gap_ms: u64,
+    pub volume: f32,
+}
+
+/// Louder sound and faster blinking after after_ms of alarm.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Escalation {
+    pub after_ms: u128,
+    pub interval_ms: u128,
+    pub sound_gap_ms: u64,
+    pub volume: f32,
+}
+
+/// How the end of a timer is signaled, by the display and optionally a
+/// repeated sound, for duration_ms or until acknowledged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmProfile {
+    pub name: String,
+    pub visual: AlarmVisual,
+    pub sound: Option<AlarmSound>,
+    pub escalation: Option<Escalation>,
+    pub duration_ms: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlarmFrame {
+    pub lit: bool,
+    pub contrast: u8,
+}
+
+impl AlarmProfile {
+    /// The alarm as it was before profiles existed: blinking and the alarm
+    /// sound for two minutes.
+    pub fn default_profile() -> Self {
+        AlarmProfile {
+            name: DEFAULT_ALARM_PROFILE.to_string(),
+            visual: AlarmVisual {
+                pattern: AlarmPattern::Blink,
+                interval_ms: 500,
+                inverted: false,
+                message: None,
+            },
+            sound: Some(AlarmSound {
+                sound: SoundId::Alarm,
+                gap_ms: 0,
+                volume: 1.0,
+            }),
+            escalation: None,
+            duration_ms: 120000,
+        }
+    }
+
+    /// Dark screen with a slowly pulsing message and a soft chime, e.g.
+    /// for bedtime reading.
+    pub fn gentle() -> Self {
+        AlarmProfile {
+            name: "gentle".to_string(),
+            visual: AlarmVisual {
+                pattern: AlarmPattern::Pulse,
+                interval_ms: 4000,
+                inverted: true,
+                message: Some("Time's up".to_string()),
+            },
+            sound: Some(AlarmSound {
+                sound: SoundId::Warning,
+                gap_ms: 5000,
+                volume: 0.3,
+            }),
+            escalation: None,
+            duration_ms: 60000,
+        }
+    }
+
+    pub fn urgent() -> Self {
+        AlarmProfile {
+            name: "urgent".to_string(),
+            visual: AlarmVisual {
+                pattern: AlarmPattern::Blink,
+                interval_ms: 500,
+                inverted: false,
+                message: Some("Time's up!".to_string()),
+            },
+            sound: Some(AlarmSound {
+                sound: SoundId::Alarm,
+                gap_ms: 1000,
+                volume: 0.6,
+            }),
+            escalation: Some(Escalation {
+                after_ms: 30000,
+                interval_ms: 150,
+                sound_gap_ms: 0,
+                volume: 1.0,
+            }),
+            duration_ms: 180000,
+        }
+    }
+
+    pub fn built_in() -> Vec<AlarmProfile> {
+        vec![
+            AlarmProfile::default_profile(),
+            AlarmProfile::gentle(),
+            AlarmProfile::urgent(),
+        ]
+    }
+
+    /// Built-in profiles are not saved, so they can't be changed.
+    pub fn is_built_in(name: &str) -> bool {
+        AlarmProfile::built_in()
+            .iter()
+            .any(|profile| profile.name == name)
+    }
+
+    pub fn is_escalated(&self, elapsed_ms: u128) -> bool {
+        self.escalation
+            .is_some_and(|escalation| elapsed_ms >= escalation.after_ms)
+    }
+
+    pub fn sound_at(&self, elapsed_ms: u128) -> Option<AlarmSound> {
+        let sound = self.sound?;
+        match self.escalation {
+            Some(escalation) if self.is_escalated(elapsed_ms) => Some(AlarmSound {
+                gap_ms: escalation.sound_gap_ms,
+                volume: escalation.volume,
+                ..sound
+            }),
+            _ => Some(sound),
+        }
+    }
+
+    fn interval_at(&self, elapsed_ms: u128) -> u128 {
+        match self.escalation {
+            Some(escalation) if self.is_escalated(elapsed_ms) => escalation.interval_ms,
+            _ => self.visual.interval_ms,
+        }
+        .max(1)
+    }
+
+    // The step of the pulse within its interval.
+    fn pulse_step(elapsed_ms: u128, interval_ms: u128) -> u128 {
+        (elapsed_ms % interval_ms) * PULSE_STEPS / interval_ms
+    }
+
+    pub fn frame_at(&self, elapsed_ms: u128) -> AlarmFrame {
+        let interval_ms = self.interval_at(elapsed_ms);
+        match self.visual.pattern {
+            AlarmPattern::Steady => AlarmFrame {
+                lit: true,
+                contrast: MAX_CONTRAST,
+            },
+            AlarmPattern::Blink => AlarmFrame {
+                lit: (elapsed_ms / interval_ms).is_multiple_of(2),
+                contrast: MAX_CONTRAST,
+            },
+            AlarmPattern::Pulse => {
+                // Triangle from full contrast down to the minimum and back up.
+                let step = AlarmProfile::pulse_step(elapsed_ms, interval_ms);
+                let phase = step as f32 / PULSE_STEPS as f32;
+                let level = f32::abs(1f32 - 2f32 * phase);
+                let range = (MAX_CONTRAST - MIN_PULSE_CONTRAST) as f32;
+                AlarmFrame {
+                    lit: true,
+                    contrast: MIN_PULSE_CONTRAST + (level * range).round() as u8,
+                }
+            }
+        }
+    }
+
+    /// The elapsed time of the next change of the frame, the escalation or
+    /// the end of the alarm, None once it is over.
+    pub fn next_change_ms(&self, elapsed_ms: u128) -> Option<u128> {
+        if elapsed_ms >= self.duration_ms {
+            return None;
+        }
+        let interval_ms = self.interval_at(elapsed_ms);
+        let interval_start_ms = elapsed_ms - elapsed_ms % interval_ms;
+        let frame_change_ms = match self.visual.pattern {
+            AlarmPattern::Steady => None,
+            AlarmPattern::Blink => Some(interval_start_ms + interval_ms),
+            AlarmPattern::Pulse => {
+                let next_step = AlarmProfile::pulse_step(elapsed_ms, interval_ms) + 1;
+                Some(interval_start_ms + (next_step * interval_ms).div_ceil(PULSE_STEPS))
+            }
+        };
+        let change_ms = frame_change_ms
+            .into_iter()
+            .chain(self.escalation.map(|escalation| escalation.after_ms))
+            .filter(|change_ms| *change_ms > elapsed_ms)
+            .fold(self.duration_ms, Ord::min);
+        Some(change_ms)
+    }
+}
+
+// The message on black, inverted for the alert.
+fn draw_alarm_frame(visual: &AlarmVisual, alert: bool, display: &mut impl DisplayControl) {
+    let fb = display.fb();
+    fb.fill_with_black();
+    if let Some(message) = &visual.message {
+        let center_y = fb.height() / 2;
+        fb.write_centered_text(message, center_y);
+    }
+    if alert {
+        fb.invert();
+    }
+    display.safe_swap();
+}
+
+/// Shows the alarm of a profile on the display and the speaker. Both are
+/// only touched when the frame or the escalation stage changes. The display
+/// blinks by its alert if it can, the frame is then only sent once.
+pub struct AlarmOutput {
+    last_frame: Option<AlarmFrame>,
+    playing: Option<(SoundId, bool)>,
+    /// Contrast restored when the alarm stops, lower during quiet hours.
+    pub rest_contrast: u8,
+    /// Contrast of a fully lit frame, the pulse is scaled down to it during
+    /// quiet hours.
+    pub peak_contrast: u8,
+}
+
+impl AlarmOutput {
+    pub fn new() -> Self {
+        AlarmOutput {
+            last_frame: None,
+            playing: None,
+            rest_contrast: DEFAULT_CONTRAST,
+            peak_contrast: MAX_CONTRAST,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.last_frame.is_some()
+    }
+
+    pub fn update(
+        &mut self,
+        profile: &AlarmProfile,
+        elapsed_ms: u128,
+        display: &mut impl DisplayControl,
+        player: &mut impl Player,
+    ) {
+        let mut frame = profile.frame_at(elapsed_ms);
+        frame.contrast =
+            (frame.contrast as u32 * self.peak_contrast as u32 / MAX_CONTRAST as u32) as u8;
+        if self.last_frame != Some(frame) {
+            let alert = frame.lit != profile.visual.inverted;
+            let last_alert = self
+                .last_frame
+                .map(|last| last.lit != profile.visual.inverted);
+            if last_alert != Some(alert) {
+                if !display.set_alert(alert) {
+                    draw_alarm_frame(&profile.visual, alert, display);
+                } else if last_alert.is_none() {
+                    draw_alarm_frame(&profile.visual, false, display);
+                }
+            }
+            if self
+                .last_frame
+                .is_none_or(|last| last.contrast != frame.contrast)
+            {
+                display.set_contrast(frame.contrast);
+            }
+            self.last_frame = Some(frame);
+        }
+
+        let is_escalated = profile.is_escalated(elapsed_ms);
+        if let Some(sound) = profile.sound_at(elapsed_ms) {
+            if self.playing != Some((sound.sound, is_escalated)) {
+                if let Some((playing_sound, _)) = self.playing {
+                    player.stop(playing_sound);
+                }
+                let remaining_ms = profile.duration_ms.saturating_sub(elapsed_ms);
+                player.play(
+                    sound.sound,
+                    PlayOptions::for_duration(Duration::from_millis(remaining_ms as u64))
+                        .with_gap(Duration::from_millis(sound.gap_ms))
+                        .with_volume(sound.volume),
+                );
+                self.playing = Some((sound.sound, is_escalated));
+            }
+        }
+    }
+
+    /// Silences the alarm and restores the contrast, the caller redraws.
+    pub fn stop(&mut self, display: &mut impl DisplayControl, player: &mut impl Player) {
+        if let Some((playing_sound, _)) = self.playing.take() {
+            player.stop(playing_sound);
+        }
+        if self.last_frame.take().is_some() {
+            display.set_alert(false);
+            display.set_contrast(self.rest_contrast);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::MockPlayer;
+    use crate::gui::display_control::{Brightness, DisplayBuffer};
+
+    /// Counts the frames sent, with or without support for the alert.
+    struct FakeDisplay {
+        fb: DisplayBuffer,
+        has_alert: bool,
+        alert: bool,
+        swap_count: usize,
+        contrasts: Vec<u8>,
+    }
+
+    impl FakeDisplay {
+        fn new(has_alert: bool) -> Self {
+            FakeDisplay {
+                fb: DisplayBuffer::new(32, 128),
+                has_alert,
+                alert: false,
+                swap_count: 0,
+                contrasts: Vec::new(),
+            }
+        }
+    }
+
+    impl DisplayControl for FakeDisplay {
+        fn init(&mut self) {}
+        fn deinit(&mut self) {}
+        fn swap(&mut self) {
+            self.swap_count += 1;
+        }
+        fn safe_swap(&mut self) {
+            self.swap();
+        }
+        fn fb(&mut self) -> &mut DisplayBuffer {
+            &mut self.fb
+        }
+        fn set_contrast(&mut self, contrast: u8) {
+            self.contrasts.push(contrast);
+        }
+        fn set_brightness(&mut self, brightness: Brightness) {}
+        fn set_inverted(&mut self, inverted: bool) {}
+        fn set_alert(&mut self, alert: bool) -> bool {
+            self.alert = alert;
+            self.has_alert
+        }
+        fn sleep(&mut self) {}
+        fn wake(&mut self) {}
+    }
+
+    fn blink(display: &mut FakeDisplay) {
+        let mut profile = AlarmProfile::default_profile();
+        profile.sound = None;
+        let mut output = AlarmOutput::new();
+        let mut player = MockPlayer::new();
+        for elapsed_ms in [0, 250, 500, 750, 1000] {
+            output.update(&profile, elapsed_ms, display, &mut player);
+        }
+        output.stop(display, &mut player);
+    }
+
+    #[test]
+    fn test_alert_blinks_without_sending_frames() {
+        let mut display = FakeDisplay::new(true);
+
+        blink(&mut display);
+
+        assert_eq!(display.swap_count, 1);
+        assert!(!display.alert);
+        assert!(display.fb.buffer.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_buffer_blinks_without_alert() {
+        let mut display = FakeDisplay::new(false);
+
+        blink(&mut display);
+
+        assert_eq!(display.swap_count, 3);
+    }
+
+    #[test]
+    fn test_peak_contrast_scales_the_frames() {
+        let mut profile = AlarmProfile::gentle();
+        profile.sound = None;
+        let mut display = FakeDisplay::new(true);
+        let mut output = AlarmOutput::new();
+        output.peak_contrast = 0x40;
+        output.rest_contrast = 0x10;
+        let mut player = MockPlayer::new();
+        for elapsed_ms in [0, 1000, 2000] {
+            output.update(&profile, elapsed_ms, &mut display, &mut player);
+        }
+        output.stop(&mut display, &mut player);
+
+        assert_eq!(display.contrasts[0], 0x40);
+        assert!(display.contrasts.iter().all(|contrast| *contrast <= 0x40));
+        assert_eq!(display.contrasts.last(), Some(&0x10));
+    }
+
+    #[test]
+    fn test_default_profile_blinks_every_half_second() {
+        let profile = AlarmProfile::default_profile();
+
+        assert!(profile.frame_at(0).lit);
+        assert!(profile.frame_at(499).lit);
+        assert!(!profile.frame_at(500).lit);
+        assert!(profile.frame_at(1000).lit);
+        assert_eq!(profile.frame_at(0).contrast, MAX_CONTRAST);
+    }
+
+    #[test]
+    fn test_pulse_fades_the_contrast() {
+        let profile = AlarmProfile::gentle();
+
+        assert_eq!(profile.frame_at(0).contrast, MAX_CONTRAST);
+        assert_eq!(profile.frame_at(2000).contrast, MIN_PULSE_CONTRAST);
+        assert!(profile.frame_at(1000).contrast < MAX_CONTRAST);
+        assert!(profile.frame_at(1000).contrast > MIN_PULSE_CONTRAST);
+        assert!(profile.frame_at(2000).lit);
+    }
+
+    #[test]
+    fn test_next_change_follows_the_pattern() {
+        let profile = AlarmProfile::default_profile();
+        assert_eq!(profile.next_change_ms(0), Some(500));
+        assert_eq!(profile.next_change_ms(750), Some(1000));
+        assert_eq!(profile.next_change_ms(119999), Some(120000));
+        assert_eq!(profile.next_change_ms(120000), None);
+
+        // The pulse changes in steps, each one its own frame.
+        let profile = AlarmProfile::gentle();
+        assert_eq!(profile.next_change_ms(0), Some(125));
+        assert_eq!(profile.next_change_ms(4000), Some(4125));
+        assert_ne!(profile.frame_at(124), profile.frame_at(125));
+
+        let profile = AlarmProfile::urgent();
+        assert_eq!(profile.next_change_ms(29800), Some(30000));
+        assert_eq!(profile.next_change_ms(30000), Some(30150));
+    }
+
+    #[test]
+    fn test_escalation_gets_louder_and_faster() {
+        let profile = AlarmProfile::urgent();
+
+        assert!(!profile.is_escalated(29999));
+        assert_eq!(profile.sound_at(0).unwrap().volume, 0.6);
+        assert!(profile.frame_at(29000).lit);
+
+        assert!(profile.is_escalated(30000));
+        assert_eq!(profile.sound_at(30000).unwrap().volume, 1.0);
+        assert_eq!(profile.sound_at(30000).unwrap().gap_ms, 0);
+        assert!(!profile.frame_at(30150).lit);
+    }
+}
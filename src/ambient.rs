@@ -0,0 +1,149 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+use crate::gui::display_control::{Brightness, DEFAULT_CONTRAST};
+use crate::schedule::ms_until;
+
+/// How the panel is driven, set over the web or by the ambient schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisplaySettings {
+    pub brightness: Brightness,
+    pub contrast: u8,
+    pub inverted: bool,
+}
+
+impl DisplaySettings {
+    pub fn new() -> Self {
+        DisplaySettings {
+            brightness: Brightness::Dim,
+            contrast: DEFAULT_CONTRAST,
+            inverted: false,
+        }
+    }
+}
+
+/// Brightness and contrast from start until the start of the next step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmbientStep {
+    pub start: NaiveTime,
+    pub brightness: Brightness,
+    pub contrast: u8,
+}
+
+/// Follows the light of the day, e.g. bright at noon and dim in the
+/// evening. The last step of the day lasts until the first one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmbientSchedule {
+    pub enabled: bool,
+    pub steps: Vec<AmbientStep>,
+}
+
+impl AmbientSchedule {
+    pub fn new() -> Self {
+        AmbientSchedule {
+            enabled: false,
+            steps: vec![
+                AmbientStep {
+                    start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                    brightness: Brightness::Normal,
+                    contrast: 0xC0,
+                },
+                AmbientStep {
+                    start: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+                    brightness: Brightness::Dim,
+                    contrast: DEFAULT_CONTRAST,
+                },
+            ],
+        }
+    }
+
+    pub fn step_at(&self, time: NaiveTime) -> Option<&AmbientStep> {
+        if !self.enabled {
+            return None;
+        }
+        self.steps
+            .iter()
+            .filter(|step| step.start <= time)
+            .max_by_key(|step| step.start)
+            .or_else(|| self.steps.iter().max_by_key(|step| step.start))
+    }
+
+    /// Time until the next step starts, None while disabled.
+    pub fn next_change_ms(&self, time: NaiveTime) -> Option<u128> {
+        if !self.enabled {
+            return None;
+        }
+        self.steps
+            .iter()
+            .map(|step| ms_until(time, step.start))
+            .min()
+    }
+
+    /// The settings with brightness and contrast of the step at the time.
+    pub fn apply(&self, settings: DisplaySettings, time: NaiveTime) -> DisplaySettings {
+        match self.step_at(time) {
+            Some(step) => DisplaySettings {
+                brightness: step.brightness,
+                contrast: step.contrast,
+                ..settings
+            },
+            None => settings,
+        }
+    }
+
+    /// Adds the step or replaces the one with the same start.
+    pub fn set_step(&mut self, step: AmbientStep) {
+        self.steps.retain(|known| known.start != step.start);
+        self.steps.push(step);
+        self.steps.sort_by_key(|step| step.start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_last_step_lasts_over_night() {
+        let mut schedule = AmbientSchedule::new();
+        let settings = DisplaySettings {
+            inverted: true,
+            ..DisplaySettings::new()
+        };
+        assert_eq!(schedule.apply(settings, time(12)), settings);
+
+        schedule.enabled = true;
+
+        assert_eq!(schedule.step_at(time(12)).unwrap().start, time(8));
+        assert_eq!(schedule.step_at(time(20)).unwrap().start, time(18));
+        assert_eq!(schedule.step_at(time(3)).unwrap().start, time(18));
+        let day = schedule.apply(settings, time(8));
+        assert_eq!(day.brightness, Brightness::Normal);
+        assert_eq!(day.contrast, 0xC0);
+        assert!(day.inverted);
+    }
+
+    #[test]
+    fn test_set_step_replaces_the_same_start() {
+        let mut schedule = AmbientSchedule::new();
+
+        schedule.set_step(AmbientStep {
+            start: time(18),
+            brightness: Brightness::Dim,
+            contrast: 0x20,
+        });
+        schedule.set_step(AmbientStep {
+            start: time(12),
+            brightness: Brightness::Normal,
+            contrast: 0xFF,
+        });
+
+        let starts: Vec<NaiveTime> = schedule.steps.iter().map(|step| step.start).collect();
+        assert_eq!(starts, vec![time(8), time(12), time(18)]);
+        assert_eq!(schedule.steps[2].contrast, 0x20);
+    }
+}
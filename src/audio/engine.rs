@@ -0,0 +1,477 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cpal::traits::StreamTrait;
+use rtrb::{Consumer, Producer, RingBuffer};
+
+use crate::audio::playback::Playback;
+use crate::audio::playback_device::create_stream;
+use crate::audio::sound::Sound;
+use crate::audio::sound_bank::SoundId;
+use crate::audio::{PlayOptions, Ticking};
+
+const COMMAND_QUEUE_CAPACITY: usize = 32;
+const MAX_VOICES: usize = 8;
+// Time the stream keeps running after the last tick, so it is not cut off.
+const TICK_TAIL: Duration = Duration::from_millis(200);
+pub const DEFAULT_VOLUME: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamFormat {
+    pub channel_count: usize,
+    pub sample_rate: u32,
+}
+
+pub enum AudioCommand {
+    Play(SoundId, Sound, PlayOptions),
+    Stop(SoundId),
+    StopAll,
+    SetVolume(f32),
+    StartTicking(Sound, Sound, Ticking),
+    StopTicking,
+}
+
+struct Voice {
+    sound_id: SoundId,
+    sound: Sound,
+    playback: Playback,
+    delay_frames: usize,
+    is_counted: bool,
+}
+
+struct TickState {
+    tick: Sound,
+    tock: Sound,
+    interval_frames: usize,
+    frames_until_tick: usize,
+    remaining_ticks: usize,
+    volume: f32,
+    is_tock: bool,
+}
+
+fn duration_to_frames(duration: Duration, sample_rate: u32) -> usize {
+    (duration.as_millis() * sample_rate as u128 / 1000) as usize
+}
+
+/// Lives inside the real-time audio callback and mixes up to MAX_VOICES
+/// sounds. It never blocks: commands arrive through a lock-free queue and
+/// sounds that are no longer needed are handed back through another one,
+/// so they are not deallocated on the audio thread.
+pub struct Renderer {
+    commands: Consumer<AudioCommand>,
+    retired_sounds: Producer<Sound>,
+    retired_voice_count: Arc<AtomicUsize>,
+    voices: Vec<Voice>,
+    tick_state: Option<TickState>,
+    volume: f32,
+}
+
+impl Renderer {
+    fn new(
+        commands: Consumer<AudioCommand>,
+        retired_sounds: Producer<Sound>,
+        retired_voice_count: Arc<AtomicUsize>,
+    ) -> Self {
+        Renderer {
+            commands,
+            retired_sounds,
+            retired_voice_count,
+            voices: Vec::with_capacity(MAX_VOICES),
+            tick_state: None,
+            volume: DEFAULT_VOLUME,
+        }
+    }
+
+    pub fn render(&mut self, data: &mut [f32]) {
+        while let Ok(command) = self.commands.pop() {
+            match command {
+                AudioCommand::Play(sound_id, sound, options) => {
+                    self.start_voice(sound_id, sound, options, 0, true);
+                }
+                AudioCommand::Stop(sound_id) => {
+                    self.retire_voices(|voice| voice.sound_id == sound_id);
+                }
+                AudioCommand::StopAll => self.retire_voices(|_| true),
+                AudioCommand::SetVolume(volume) => self.volume = volume,
+                AudioCommand::StartTicking(tick, tock, ticking) => {
+                    let sample_rate = tick.sample_rate();
+                    self.retire_tick_state();
+                    self.tick_state = Some(TickState {
+                        tick,
+                        tock,
+                        interval_frames: usize::max(
+                            1,
+                            duration_to_frames(ticking.interval, sample_rate),
+                        ),
+                        frames_until_tick: duration_to_frames(ticking.first_tick_in, sample_rate),
+                        remaining_ticks: ticking.tick_count,
+                        volume: ticking.volume,
+                        is_tock: false,
+                    });
+                }
+                AudioCommand::StopTicking => {
+                    self.retire_tick_state();
+                    self.retire_voices(|voice| {
+                        voice.sound_id == SoundId::Tick || voice.sound_id == SoundId::Tock
+                    });
+                }
+            }
+        }
+
+        data.fill(0f32);
+        self.schedule_ticks(data.len());
+        for voice in self.voices.iter_mut() {
+            let channel_count = voice.sound.channel_count();
+            let offset = usize::min(voice.delay_frames * channel_count, data.len());
+            voice.playback.mix(&voice.sound, &mut data[offset..], self.volume);
+            voice.delay_frames -= offset / channel_count;
+        }
+        for data_out in data.iter_mut() {
+            *data_out = data_out.clamp(-1f32, 1f32);
+        }
+
+        self.retire_voices(|voice| voice.playback.is_finished());
+    }
+
+    fn start_voice(
+        &mut self,
+        sound_id: SoundId,
+        sound: Sound,
+        options: PlayOptions,
+        delay_frames: usize,
+        is_counted: bool,
+    ) {
+        if self.voices.len() == MAX_VOICES {
+            // Make room by dropping the oldest voice.
+            let oldest_voice = self.voices.remove(0);
+            self.retire(oldest_voice);
+        }
+        self.voices.push(Voice {
+            sound_id,
+            sound,
+            playback: Playback::new(options),
+            delay_frames,
+            is_counted,
+        });
+    }
+
+    // Starts a voice for each tick that falls into the next sample_count
+    // samples, delayed to the exact frame of the tick.
+    fn schedule_ticks(&mut self, sample_count: usize) {
+        let mut tick_state = match self.tick_state.take() {
+            Some(tick_state) => tick_state,
+            None => return,
+        };
+        let frame_count = sample_count / tick_state.tick.channel_count();
+
+        while tick_state.remaining_ticks > 0 && tick_state.frames_until_tick < frame_count {
+            let (sound_id, sound) = match tick_state.is_tock {
+                true => (SoundId::Tock, tick_state.tock.clone()),
+                false => (SoundId::Tick, tick_state.tick.clone()),
+            };
+            let options = PlayOptions::once().with_volume(tick_state.volume);
+            self.start_voice(sound_id, sound, options, tick_state.frames_until_tick, false);
+            tick_state.is_tock = !tick_state.is_tock;
+            tick_state.frames_until_tick += tick_state.interval_frames;
+            tick_state.remaining_ticks -= 1;
+        }
+
+        if tick_state.remaining_ticks > 0 {
+            tick_state.frames_until_tick -= frame_count;
+            self.tick_state = Some(tick_state);
+        } else {
+            self.retire_tick_state_sounds(tick_state);
+        }
+    }
+
+    fn retire_tick_state(&mut self) {
+        if let Some(tick_state) = self.tick_state.take() {
+            self.retire_tick_state_sounds(tick_state);
+        }
+    }
+
+    fn retire_tick_state_sounds(&mut self, tick_state: TickState) {
+        let _ = self.retired_sounds.push(tick_state.tick);
+        let _ = self.retired_sounds.push(tick_state.tock);
+    }
+
+    fn retire_voices(&mut self, condition: impl Fn(&Voice) -> bool) {
+        let mut index = 0;
+        while index < self.voices.len() {
+            if condition(&self.voices[index]) {
+                let voice = self.voices.remove(index);
+                self.retire(voice);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    fn retire(&mut self, voice: Voice) {
+        if voice.is_counted {
+            self.retired_voice_count.fetch_add(1, Ordering::AcqRel);
+        }
+        // If the queue is full the sound is dropped right here, which is
+        // only a problem if it was the last reference.
+        let _ = self.retired_sounds.push(voice.sound);
+    }
+}
+
+/// Owns the single, long-lived output stream and controls it through the
+/// command queue. The stream is paused while nothing is playing.
+pub struct AudioEngine {
+    stream: cpal::Stream,
+    format: StreamFormat,
+    commands: Producer<AudioCommand>,
+    retired_sounds: Consumer<Sound>,
+    started_voice_count: usize,
+    retired_voice_count: Arc<AtomicUsize>,
+    ticking_until: Option<Instant>,
+    is_stream_paused: bool,
+}
+
+impl AudioEngine {
+    pub fn new() -> Option<Self> {
+        let (commands_producer, commands_consumer) = RingBuffer::new(COMMAND_QUEUE_CAPACITY);
+        let (retired_producer, retired_consumer) = RingBuffer::new(COMMAND_QUEUE_CAPACITY);
+        let retired_voice_count = Arc::new(AtomicUsize::new(0));
+
+        let renderer = Renderer::new(
+            commands_consumer,
+            retired_producer,
+            Arc::clone(&retired_voice_count),
+        );
+
+        let (stream, format) = match create_stream(renderer) {
+            Ok(stream_and_format) => stream_and_format,
+            Err(error) => {
+                eprintln!("Audio is disabled: {}", error);
+                return None;
+            }
+        };
+        if let Err(error) = stream.pause() {
+            eprintln!("Audio is disabled: Unable to pause the stream: {}", error);
+            return None;
+        }
+
+        Some(AudioEngine {
+            stream,
+            format,
+            commands: commands_producer,
+            retired_sounds: retired_consumer,
+            started_voice_count: 0,
+            retired_voice_count,
+            ticking_until: None,
+            is_stream_paused: true,
+        })
+    }
+
+    pub fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    pub fn play(&mut self, sound_id: SoundId, sound: Sound, options: PlayOptions) {
+        if self.send(AudioCommand::Play(sound_id, sound, options)) {
+            self.started_voice_count += 1;
+        }
+        self.resume_stream();
+    }
+
+    pub fn start_ticking(&mut self, tick: Sound, tock: Sound, ticking: Ticking) {
+        if self.send(AudioCommand::StartTicking(tick, tock, ticking)) {
+            let last_tick_in = ticking.first_tick_in
+                + ticking.interval * ticking.tick_count.saturating_sub(1) as u32;
+            self.ticking_until = Some(Instant::now() + last_tick_in + TICK_TAIL);
+        }
+        self.resume_stream();
+    }
+
+    pub fn stop_ticking(&mut self) {
+        if self.ticking_until.take().is_some() {
+            self.send(AudioCommand::StopTicking);
+        }
+    }
+
+    pub fn stop(&mut self, sound_id: SoundId) {
+        if self.is_playing() {
+            self.send(AudioCommand::Stop(sound_id));
+        }
+    }
+
+    pub fn stop_all(&mut self) {
+        if self.is_playing() {
+            self.send(AudioCommand::StopAll);
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.send(AudioCommand::SetVolume(volume));
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.retired_voice_count.load(Ordering::Acquire) < self.started_voice_count
+    }
+
+    pub fn is_ticking(&self) -> bool {
+        self.ticking_until
+            .is_some_and(|ticking_until| Instant::now() < ticking_until)
+    }
+
+    /// Frees sounds handed back by the audio callback and pauses the
+    /// stream once nothing is playing anymore.
+    pub fn update(&mut self) {
+        while self.retired_sounds.pop().is_ok() {}
+
+        if !self.is_ticking() {
+            self.ticking_until = None;
+        }
+
+        // Wait until the callback has processed all commands.
+        if !self.is_stream_paused
+            && !self.is_playing()
+            && !self.is_ticking()
+            && self.commands.slots() == COMMAND_QUEUE_CAPACITY
+        {
+            self.stream
+                .pause()
+                .expect("Failed to pause the audio stream.");
+            self.is_stream_paused = true;
+        }
+    }
+
+    fn resume_stream(&mut self) {
+        if self.is_stream_paused {
+            self.stream
+                .play()
+                .expect("Failed to play the audio stream.");
+            self.is_stream_paused = false;
+        }
+    }
+
+    fn send(&mut self, command: AudioCommand) -> bool {
+        let is_sent = self.commands.push(command).is_ok();
+        if !is_sent {
+            eprintln!("Audio command queue is full, dropping command.");
+        }
+        is_sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_new_renderer() -> (Renderer, Producer<AudioCommand>, Consumer<Sound>) {
+        let (commands_producer, commands_consumer) = RingBuffer::new(COMMAND_QUEUE_CAPACITY);
+        let (retired_producer, retired_consumer) = RingBuffer::new(COMMAND_QUEUE_CAPACITY);
+        let mut renderer = Renderer::new(
+            commands_consumer,
+            retired_producer,
+            Arc::new(AtomicUsize::new(0)),
+        );
+        renderer.volume = 1f32;
+        (renderer, commands_producer, retired_consumer)
+    }
+
+    fn play(commands: &mut Producer<AudioCommand>, sound_id: SoundId, frame_count: usize) {
+        let sound = Sound::new(vec![0.25f32; frame_count], 1, 1000);
+        commands
+            .push(AudioCommand::Play(sound_id, sound, PlayOptions::once()))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_renderer_plays_and_retires_sounds() {
+        let (mut renderer, mut commands, mut retired_sounds) = get_new_renderer();
+        let mut data = [0f32; 200];
+
+        play(&mut commands, SoundId::Start, 100);
+        renderer.render(&mut data);
+
+        assert!(data[50] > 0f32);
+        assert_eq!(data[150], 0f32);
+        assert_eq!(renderer.retired_voice_count.load(Ordering::Acquire), 1);
+        assert!(retired_sounds.pop().is_ok());
+    }
+
+    #[test]
+    fn test_renderer_mixes_voices() {
+        let (mut renderer, mut commands, _retired_sounds) = get_new_renderer();
+        let mut data = [0f32; 100];
+
+        play(&mut commands, SoundId::Start, 1000);
+        play(&mut commands, SoundId::Alarm, 1000);
+        renderer.render(&mut data);
+
+        assert_eq!(data[50], 0.5f32);
+    }
+
+    #[test]
+    fn test_renderer_stops_single_sounds_and_sets_volume() {
+        let (mut renderer, mut commands, mut retired_sounds) = get_new_renderer();
+        let mut data = [0f32; 100];
+
+        play(&mut commands, SoundId::Start, 1000);
+        play(&mut commands, SoundId::Alarm, 1000);
+        commands.push(AudioCommand::Stop(SoundId::Alarm)).unwrap();
+        commands.push(AudioCommand::SetVolume(0.5)).unwrap();
+        renderer.render(&mut data);
+        assert_eq!(data[50], 0.125f32);
+        assert!(retired_sounds.pop().is_ok());
+
+        commands.push(AudioCommand::StopAll).unwrap();
+        renderer.render(&mut data);
+        assert!(data.iter().all(|sample| *sample == 0f32));
+        assert!(retired_sounds.pop().is_ok());
+    }
+
+    #[test]
+    fn test_renderer_drops_the_oldest_voice_when_full() {
+        let (mut renderer, mut commands, _retired_sounds) = get_new_renderer();
+        let mut data = [0f32; 100];
+
+        play(&mut commands, SoundId::Alarm, 1000);
+        for _ in 0..MAX_VOICES {
+            play(&mut commands, SoundId::Start, 1000);
+        }
+        renderer.render(&mut data);
+
+        assert_eq!(renderer.voices.len(), MAX_VOICES);
+        assert!(renderer
+            .voices
+            .iter()
+            .all(|voice| voice.sound_id == SoundId::Start));
+    }
+
+    #[test]
+    fn test_renderer_schedules_ticks_sample_accurately() {
+        let (mut renderer, mut commands, _retired_sounds) = get_new_renderer();
+        let tick = Sound::new(vec![0.25f32; 20], 1, 1000);
+        let tock = Sound::new(vec![-0.25f32; 20], 1, 1000);
+        let ticking = Ticking {
+            interval: Duration::from_millis(100),
+            first_tick_in: Duration::from_millis(30),
+            tick_count: 3,
+            volume: 1f32,
+        };
+        let mut data = [0f32; 1000];
+
+        commands
+            .push(AudioCommand::StartTicking(tick, tock, ticking))
+            .unwrap();
+        renderer.render(&mut data[..50]);
+        renderer.render(&mut data[50..]);
+
+        let first_sounding_frame = |range: std::ops::Range<usize>| {
+            range.clone().find(|frame| data[*frame] != 0f32)
+        };
+        assert_eq!(first_sounding_frame(0..100), Some(31));
+        assert!(data[40] > 0f32);
+        assert_eq!(first_sounding_frame(100..200), Some(131));
+        assert!(data[140] < 0f32);
+        assert_eq!(first_sounding_frame(200..300), Some(231));
+        assert_eq!(first_sounding_frame(300..1000), None);
+        assert!(renderer.tick_state.is_none());
+    }
+}
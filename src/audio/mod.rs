@@ -1,11 +1,89 @@
-pub mod wav_player;
+pub mod engine;
+pub mod sound_bank;
+pub mod sound_player;
 pub mod playback_device;
+mod playback;
+mod sound;
+pub mod synth;
 mod wav_file;
 
 use std::time::Duration;
 
+use crate::audio::sound_bank::SoundId;
+
+/// Describes how often and how long a sound is played.
+///
+/// A playback ends as soon as either the loop count or the duration is
+/// reached. If neither is given the sound repeats until it is stopped.
+/// The volume is relative to the volume of the player.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayOptions {
+    pub loop_count: Option<u32>,
+    pub duration: Option<Duration>,
+    pub gap: Duration,
+    pub volume: f32,
+}
+
+impl PlayOptions {
+    pub fn once() -> Self {
+        PlayOptions::repeat(1)
+    }
+
+    pub fn repeat(loop_count: u32) -> Self {
+        PlayOptions {
+            loop_count: Some(loop_count),
+            duration: None,
+            gap: Duration::ZERO,
+            volume: 1f32,
+        }
+    }
+
+    pub fn for_duration(duration: Duration) -> Self {
+        PlayOptions {
+            loop_count: None,
+            duration: Some(duration),
+            gap: Duration::ZERO,
+            volume: 1f32,
+        }
+    }
+
+    pub fn with_gap(mut self, gap: Duration) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        PlayOptions::once()
+    }
+}
+
+/// Alternating tick and tock sounds with a fixed interval, scheduled
+/// sample-accurately by the audio engine. The first tick is played after
+/// first_tick_in, the ticking stops by itself after tick_count ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ticking {
+    pub interval: Duration,
+    pub first_tick_in: Duration,
+    pub tick_count: usize,
+    pub volume: f32,
+}
+
+#[cfg_attr(test, mockall::automock)]
 pub trait Player {
-    fn play(&mut self, duration: Duration);
-    fn stop(& mut self);
+    fn play(&mut self, sound: SoundId, options: PlayOptions);
+    fn stop(&mut self, sound: SoundId);
+    fn stop_all(&mut self);
+    fn set_volume(&mut self, volume: f32);
+    fn start_ticking(&mut self, ticking: Ticking);
+    fn stop_ticking(&mut self);
     fn is_ready(&self) -> bool;
-}
\ No newline at end of file
+    fn is_playing(&self) -> bool;
+    fn update(&mut self);
+}
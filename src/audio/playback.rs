@@ -0,0 +1,198 @@
+use crate::audio::sound::Sound;
+use crate::audio::PlayOptions;
+
+const FADE_MS: usize = 10;
+
+/// Cursor over a Sound that honours the PlayOptions.
+///
+/// Every playback starts with a short fade-in and ends with a short
+/// fade-out to avoid clicks. If there is a gap between repetitions,
+/// each repetition is faded in and out separately.
+pub struct Playback {
+    options: PlayOptions,
+    frame: usize,
+    repetition: u32,
+    elapsed_frames: usize,
+    finished: bool,
+}
+
+fn ms_to_frames(ms: u128, sample_rate: u32) -> usize {
+    (ms * sample_rate as u128 / 1000) as usize
+}
+
+fn ramp(position: usize, fade_frames: usize) -> f32 {
+    if fade_frames == 0 {
+        1f32
+    } else {
+        f32::min(1f32, position as f32 / fade_frames as f32)
+    }
+}
+
+impl Playback {
+    pub fn new(options: PlayOptions) -> Self {
+        Playback {
+            options,
+            frame: 0,
+            repetition: 0,
+            elapsed_frames: 0,
+            finished: options.loop_count == Some(0)
+                || options.duration.is_some_and(|duration| duration.is_zero()),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Adds the next samples of the sound to data, so several playbacks
+    /// can be mixed into the same buffer.
+    pub fn mix(&mut self, sound: &Sound, data: &mut [f32], volume_factor: f32) {
+        let channel_count = usize::max(1, sound.channel_count());
+        for frame_out in data.chunks_mut(channel_count) {
+            let gain = self.advance(sound);
+            for (channel, data_out) in frame_out.iter_mut().enumerate() {
+                if let Some((frame, gain)) = gain {
+                    *data_out +=
+                        sound.sample(frame, channel) * gain * self.options.volume * volume_factor;
+                }
+            }
+        }
+    }
+
+    // Moves the cursor by one frame. Returns the frame of the sound to
+    // output together with its gain, or None if silence is to be output.
+    fn advance(&mut self, sound: &Sound) -> Option<(usize, f32)> {
+        if self.finished {
+            return None;
+        }
+
+        let sample_rate = sound.sample_rate();
+        let sample_frames = sound.frame_count();
+        let gap_frames = ms_to_frames(self.options.gap.as_millis(), sample_rate);
+        let fade_frames = usize::min(
+            ms_to_frames(FADE_MS as u128, sample_rate),
+            sample_frames / 2,
+        );
+        let total_frames = self
+            .options
+            .duration
+            .map(|duration| ms_to_frames(duration.as_millis(), sample_rate));
+
+        if sample_frames == 0 || total_frames.is_some_and(|total| self.elapsed_frames >= total) {
+            self.finished = true;
+            return None;
+        }
+
+        if self.frame >= sample_frames + gap_frames {
+            self.frame = 0;
+            self.repetition += 1;
+            if self
+                .options
+                .loop_count
+                .is_some_and(|count| self.repetition >= count)
+            {
+                self.finished = true;
+                return None;
+            }
+        }
+
+        let output = if self.frame < sample_frames {
+            let is_last_repetition = self
+                .options
+                .loop_count
+                .is_some_and(|count| self.repetition + 1 >= count);
+            let mut gain = 1f32;
+            if self.repetition == 0 || gap_frames > 0 {
+                gain = f32::min(gain, ramp(self.frame, fade_frames));
+            }
+            if is_last_repetition || gap_frames > 0 {
+                gain = f32::min(gain, ramp(sample_frames - self.frame, fade_frames));
+            }
+            if let Some(total_frames) = total_frames {
+                gain = f32::min(gain, ramp(total_frames - self.elapsed_frames, fade_frames));
+            }
+            Some((self.frame, gain))
+        } else {
+            None
+        };
+
+        self.frame += 1;
+        self.elapsed_frames += 1;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // Creates a mono sound with 1000 samples per second holding the
+    // given number of frames at full positive amplitude.
+    fn get_new_sound(frame_count: usize) -> Sound {
+        Sound::new(vec![1f32; frame_count], 1, 1000)
+    }
+
+    fn count_sounding_frames(data: &[f32]) -> usize {
+        data.iter().filter(|sample| **sample > 0f32).count()
+    }
+
+    #[test]
+    fn test_play_once_stops_after_the_sample() {
+        let sound = get_new_sound(100);
+        let mut playback = Playback::new(PlayOptions::once());
+        let mut data = [0f32; 300];
+
+        playback.mix(&sound, &mut data, 1f32);
+
+        assert_eq!(count_sounding_frames(&data[..100]), 99);
+        assert_eq!(count_sounding_frames(&data[100..]), 0);
+        assert!(playback.is_finished());
+    }
+
+    #[test]
+    fn test_loop_count_and_gap() {
+        let sound = get_new_sound(100);
+        let options = PlayOptions::repeat(3).with_gap(Duration::from_millis(50));
+        let mut playback = Playback::new(options);
+        let mut data = [0f32; 500];
+
+        playback.mix(&sound, &mut data, 1f32);
+
+        assert_eq!(count_sounding_frames(&data[100..150]), 0);
+        assert!(count_sounding_frames(&data[150..250]) > 0);
+        assert_eq!(count_sounding_frames(&data[250..300]), 0);
+        assert!(count_sounding_frames(&data[300..400]) > 0);
+        assert_eq!(count_sounding_frames(&data[400..]), 0);
+        assert!(playback.is_finished());
+    }
+
+    #[test]
+    fn test_duration_loops_and_cuts_the_sample() {
+        let sound = get_new_sound(100);
+        let options = PlayOptions::for_duration(Duration::from_millis(250));
+        let mut playback = Playback::new(options);
+        let mut data = [0f32; 400];
+
+        playback.mix(&sound, &mut data, 1f32);
+
+        assert_eq!(count_sounding_frames(&data[100..200]), 100);
+        assert!(count_sounding_frames(&data[200..250]) > 0);
+        assert_eq!(count_sounding_frames(&data[250..]), 0);
+        assert!(playback.is_finished());
+    }
+
+    #[test]
+    fn test_fade_in_and_fade_out() {
+        let sound = get_new_sound(100);
+        let mut playback = Playback::new(PlayOptions::once());
+        let mut data = [0f32; 100];
+
+        playback.mix(&sound, &mut data, 1f32);
+
+        assert_eq!(data[0], 0f32);
+        assert!(data[5] > 0f32 && data[5] < data[10]);
+        assert!(data[50] > 0.99f32);
+        assert!(data[95] > 0f32 && data[95] < data[90]);
+    }
+}
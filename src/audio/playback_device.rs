@@ -1,18 +1,24 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Data, Sample, SampleFormat, SampleRate};
-use std::fs::read;
-use std::{time::Duration, sync::{RwLock, Arc}};
 
-use crate::audio::wav_file::WavFile;
+use crate::audio::engine::{Renderer, StreamFormat};
+
+const PREFERRED_CHANNEL_COUNT: cpal::ChannelCount = 2;
+const PREFERRED_SAMPLE_RATE: u32 = 44100;
 
 fn find_audio_output_device(start_of_name: Option<&str>) -> Option<cpal::Device> {
-    cpal::default_host()
-        .output_devices()
-        .unwrap()
-        .find(|device| {
-            (start_of_name.is_none() || device.name().unwrap().starts_with(start_of_name.unwrap()))
-                && device.supported_output_configs().unwrap().next().is_some()
-        })
+    cpal::default_host().output_devices().ok()?.find(|device| {
+        let is_named = match start_of_name {
+            Some(start_of_name) => device
+                .name()
+                .is_ok_and(|name| name.starts_with(start_of_name)),
+            None => true,
+        };
+        is_named
+            && device
+                .supported_output_configs()
+                .is_ok_and(|mut configs| configs.next().is_some())
+    })
 }
 
 fn find_any_audio_output_device() -> Option<cpal::Device> {
@@ -27,60 +33,64 @@ fn find_any_audio_output_device() -> Option<cpal::Device> {
         })
 }
 
-pub fn create_stream(wav_file: Arc<RwLock<Option<WavFile>>>) -> cpal::Stream {
+/// Builds the one output stream of the application. The stream is fed by
+/// the Renderer, which is moved into the real-time audio callback.
+/// Returns why it failed, e.g. there is no output device or the device has
+/// no config with f32 samples.
+pub fn create_stream(renderer: Renderer) -> Result<(cpal::Stream, StreamFormat), String> {
     let device = find_audio_output_device(Some("sysdefault"))
         .or_else(|| find_audio_output_device(None))
-        .unwrap();
-
-    println!("{}", device.name().unwrap());
+        .ok_or_else(|| "No audio output device found.".to_string())?;
+    let device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
 
-    let mut supported_configs = device
+    let supported_configs: Vec<_> = device
         .supported_output_configs()
-        .expect("Error while querying audio configs");
-
-    let (channel_count, sample_rate) = {
-        let wav_file_lock = wav_file.read().unwrap();
-        match &*wav_file_lock {
-            Some(wav_file) => (wav_file.channel_count(), wav_file.sample_rate()),
-            None => {
-                eprintln!("WavFile data is None!");
-                (0, 44100)
-            }
-        }
-    };
+        .map_err(|error| {
+            format!("Unable to query the configs of audio device {}: {}", device_name, error)
+        })?
+        .filter(|config_range| config_range.sample_format() == cpal::SampleFormat::F32)
+        .collect();
 
+    let sample_rate = cpal::SampleRate(PREFERRED_SAMPLE_RATE);
     let supported_stream_config = supported_configs
+        .iter()
         .find(|config_range| {
-            config_range.channels() == channel_count as cpal::ChannelCount
-                && config_range.min_sample_rate() <= cpal::SampleRate(sample_rate)
-                && config_range.max_sample_rate() >= cpal::SampleRate(sample_rate)
-                && config_range.sample_format() == cpal::SampleFormat::F32
+            config_range.channels() == PREFERRED_CHANNEL_COUNT
+                && config_range.min_sample_rate() <= sample_rate
+                && config_range.max_sample_rate() >= sample_rate
         })
-        .unwrap()
-        .with_sample_rate(cpal::SampleRate(sample_rate));
+        .map(|config_range| (*config_range).with_sample_rate(sample_rate))
+        .or_else(|| {
+            supported_configs
+                .first()
+                .map(|config_range| (*config_range).with_max_sample_rate())
+        })
+        .ok_or(format!(
+            "Audio device {} has no output config with f32 samples.",
+            device_name
+        ))?;
+    let stream_config = supported_stream_config.config();
 
-    println!("supported_stream_config {:?}", supported_stream_config);
-    let stream_config = supported_stream_config.config().clone();
-    println!("stream_config {:?}", stream_config);
+    let format = StreamFormat {
+        channel_count: stream_config.channels as usize,
+        sample_rate: stream_config.sample_rate.0,
+    };
 
-    device
+    let mut renderer = renderer;
+    let stream = device
         .build_output_stream(
             &stream_config,
             move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
-                if let Ok(wav_file_guard) = &mut wav_file.write() {
-                    if let Some(wav_file) = wav_file_guard.as_mut() {
-                        wav_file.get_samples(data, 0.05);
-                    } else {
-                        eprintln!("WavFile is None.");
-                    }
-                } else {
-                    eprintln!("Failed to acquire read lock for WavFile.");
-                }
+                renderer.render(data);
             },
             |error| {
-                println!("Audio output error: {}", error);
+                eprintln!("Audio output error: {}", error);
             },
             None,
         )
-        .unwrap()
+        .map_err(|error| {
+            format!("Unable to build the stream of audio device {}: {}", device_name, error)
+        })?;
+
+    Ok((stream, format))
 }
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::audio::wav_file::WavFile;
+
+/// Decoded, immutable audio data with interleaved f32 samples.
+///
+/// Cloning a Sound is cheap, the samples are shared. This allows handing
+/// a Sound to the audio callback without copying or locking.
+#[derive(Clone)]
+pub struct Sound {
+    samples: Arc<[f32]>,
+    channel_count: usize,
+    sample_rate: u32,
+}
+
+impl Sound {
+    pub fn new(samples: Vec<f32>, channel_count: usize, sample_rate: u32) -> Self {
+        Sound {
+            samples: samples.into(),
+            channel_count: usize::max(1, channel_count),
+            sample_rate,
+        }
+    }
+
+    pub fn from_wav_file(wav_file: &WavFile) -> Self {
+        let channel_count = wav_file.channel_count() as usize;
+        let mut samples = Vec::with_capacity(wav_file.frame_count() * channel_count);
+        for frame in 0..wav_file.frame_count() {
+            for channel in 0..channel_count {
+                samples.push(wav_file.sample(frame, channel));
+            }
+        }
+        Sound::new(samples, channel_count, wav_file.sample_rate())
+    }
+
+    /// Returns the Sound resampled and remapped to the given format.
+    /// Missing channels repeat the last available one, surplus ones are
+    /// dropped. Resampling uses linear interpolation, which is good enough
+    /// for chimes and alarms.
+    pub fn converted(&self, channel_count: usize, sample_rate: u32) -> Sound {
+        let channel_count = usize::max(1, channel_count);
+        if channel_count == self.channel_count && sample_rate == self.sample_rate {
+            return self.clone();
+        }
+
+        let source_frames = self.frame_count();
+        let frame_count = if self.sample_rate == 0 {
+            0
+        } else {
+            (source_frames as u64 * sample_rate as u64 / self.sample_rate as u64) as usize
+        };
+        let step = self.sample_rate as f64 / sample_rate as f64;
+
+        let mut samples = Vec::with_capacity(frame_count * channel_count);
+        for frame in 0..frame_count {
+            let position = frame as f64 * step;
+            let source_frame = position as usize;
+            let weight = (position - source_frame as f64) as f32;
+            for channel in 0..channel_count {
+                let current = self.sample(source_frame, channel);
+                let next = if source_frame + 1 < source_frames {
+                    self.sample(source_frame + 1, channel)
+                } else {
+                    current
+                };
+                samples.push(current + (next - current) * weight);
+            }
+        }
+        Sound::new(samples, channel_count, sample_rate)
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.samples.len() / self.channel_count
+    }
+
+    pub fn sample(&self, frame: usize, channel: usize) -> f32 {
+        let channel = usize::min(channel, self.channel_count - 1);
+        self.samples
+            .get(frame * self.channel_count + channel)
+            .copied()
+            .unwrap_or(0f32)
+    }
+}
+
+impl fmt::Debug for Sound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sound")
+            .field("channel_count", &self.channel_count)
+            .field("sample_rate", &self.sample_rate)
+            .field("samples (count)", &self.samples.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversion_from_mono_to_stereo_duplicates_the_channel() {
+        let sound = Sound::new(vec![0.1, 0.2, 0.3], 1, 1000);
+
+        let converted = sound.converted(2, 1000);
+
+        assert_eq!(converted.frame_count(), 3);
+        for frame in 0..3 {
+            assert_eq!(converted.sample(frame, 0), sound.sample(frame, 0));
+            assert_eq!(converted.sample(frame, 1), sound.sample(frame, 0));
+        }
+    }
+
+    #[test]
+    fn test_conversion_of_the_sample_rate_interpolates() {
+        let sound = Sound::new(vec![0.0, 1.0, 0.0, -1.0], 1, 1000);
+
+        let converted = sound.converted(1, 2000);
+
+        assert_eq!(converted.frame_count(), 8);
+        assert_eq!(converted.sample(1, 0), 0.5);
+        assert_eq!(converted.sample(2, 0), 1.0);
+        assert_eq!(converted.sample(5, 0), -0.5);
+    }
+}
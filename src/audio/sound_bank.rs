@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::audio::engine::StreamFormat;
+use crate::audio::sound::Sound;
+use crate::audio::synth::{parse_melody, render_melody, Instrument, DEFAULT_TEMPO_BPM};
+use crate::audio::wav_file::WavFile;
+use serde::{Deserialize, Serialize};
+
+const FALLBACK_SAMPLE_RATE: u32 = 44100;
+
+/// The sound effects known to the hourglass. Each one is loaded from a wav
+/// file named after the sound, e.g. "./audio/alarm.wav". Without a wav file
+/// a melody file, e.g. "./audio/alarm.melody", is synthesized instead and
+/// without both a built-in melody is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundId {
+    Start,
+    MinuteAdded,
+    Warning,
+    OneMinuteWarning,
+    Alarm,
+    Goodbye,
+    Tick,
+    Tock,
+}
+
+impl SoundId {
+    pub const ALL: [SoundId; 8] = [
+        SoundId::Start,
+        SoundId::MinuteAdded,
+        SoundId::Warning,
+        SoundId::OneMinuteWarning,
+        SoundId::Alarm,
+        SoundId::Goodbye,
+        SoundId::Tick,
+        SoundId::Tock,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SoundId::Start => "start",
+            SoundId::MinuteAdded => "minute_added",
+            SoundId::Warning => "warning",
+            SoundId::OneMinuteWarning => "one_minute_warning",
+            SoundId::Alarm => "alarm",
+            SoundId::Goodbye => "goodbye",
+            SoundId::Tick => "tick",
+            SoundId::Tock => "tock",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SoundId> {
+        SoundId::ALL.iter().copied().find(|id| id.name() == name)
+    }
+
+    fn default_melody(&self) -> (&'static str, Instrument) {
+        match self {
+            SoundId::Start => ("C5:16 E5:16 G5:8", Instrument::chime()),
+            SoundId::MinuteAdded => ("G5:32", Instrument::beep()),
+            SoundId::Warning => ("E5:8", Instrument::chime()),
+            SoundId::OneMinuteWarning => ("A5:8 R:16 A5:8", Instrument::chime()),
+            SoundId::Alarm => ("C6:8 G5:8 C6:8 G5:8 R:4", Instrument::beep()),
+            SoundId::Goodbye => ("G5:8 E5:8 C5:4", Instrument::chime()),
+            SoundId::Tick => ("E6:64", Instrument::click()),
+            SoundId::Tock => ("B5:64", Instrument::click()),
+        }
+    }
+}
+
+fn synthesize(melody: &str, instrument: &Instrument, sample_rate: u32) -> Option<Sound> {
+    match parse_melody(melody, DEFAULT_TEMPO_BPM) {
+        Ok(notes) => Some(render_melody(&notes, instrument, sample_rate)),
+        Err(error) => {
+            eprintln!("Unable to parse melody \"{}\": {}", melody, error);
+            None
+        }
+    }
+}
+
+pub struct SoundBank {
+    sounds: HashMap<SoundId, Sound>,
+}
+
+impl SoundBank {
+    pub fn new() -> Self {
+        SoundBank {
+            sounds: HashMap::new(),
+        }
+    }
+
+    /// Loads all sounds found in the directory and converts them to the
+    /// format of the output stream. Missing files are synthesized.
+    pub fn load(directory: &str, format: Option<StreamFormat>) -> Self {
+        let sample_rate = format.map_or(FALLBACK_SAMPLE_RATE, |format| format.sample_rate);
+        let mut bank = SoundBank::new();
+        for id in SoundId::ALL {
+            let wav_path = Path::new(directory).join(format!("{}.wav", id.name()));
+            let melody_path = Path::new(directory).join(format!("{}.melody", id.name()));
+            let (default_melody, instrument) = id.default_melody();
+
+            let sound = if wav_path.exists() {
+                Some(Sound::from_wav_file(&WavFile::new(
+                    wav_path.display().to_string(),
+                )))
+            } else if let Ok(melody) = fs::read_to_string(&melody_path) {
+                synthesize(&melody, &instrument, sample_rate)
+            } else {
+                None
+            };
+            let sound = sound.or_else(|| synthesize(default_melody, &instrument, sample_rate));
+
+            if let Some(mut sound) = sound {
+                if let Some(format) = format {
+                    sound = sound.converted(format.channel_count, format.sample_rate);
+                }
+                bank.insert(id, sound);
+            }
+        }
+        bank
+    }
+
+    pub fn insert(&mut self, id: SoundId, sound: Sound) {
+        self.sounds.insert(id, sound);
+    }
+
+    pub fn get(&self, id: SoundId) -> Option<&Sound> {
+        self.sounds.get(&id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sound_names_round_trip() {
+        for id in SoundId::ALL {
+            assert_eq!(SoundId::from_name(id.name()), Some(id));
+        }
+        assert_eq!(SoundId::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn test_default_melodies_are_valid() {
+        for id in SoundId::ALL {
+            let (melody, _) = id.default_melody();
+            assert!(parse_melody(melody, DEFAULT_TEMPO_BPM).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_loading_from_a_missing_directory_synthesizes_all_sounds() {
+        let bank = SoundBank::load("./does_not_exist/", None);
+        for id in SoundId::ALL {
+            assert!(bank.get(id).unwrap().frame_count() > 0);
+        }
+    }
+}
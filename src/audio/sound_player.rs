@@ -0,0 +1,98 @@
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+use crate::audio::engine::AudioEngine;
+use crate::audio::sound_bank::{SoundBank, SoundId};
+use crate::audio::{PlayOptions, Player, Ticking};
+
+pub struct SoundPlayer {
+    engine: Option<AudioEngine>,
+    sound_bank: Arc<OnceLock<SoundBank>>,
+}
+
+impl SoundPlayer {
+    pub fn new(sound_directory: String) -> Self {
+        let player = SoundPlayer {
+            engine: AudioEngine::new(),
+            sound_bank: Arc::new(OnceLock::new()),
+        };
+
+        // Decode and convert the files off the main thread, the stream
+        // format is known up front because the stream lives forever.
+        let format = player.engine.as_ref().map(|engine| engine.format());
+        let sound_bank_ref = Arc::clone(&player.sound_bank);
+        thread::spawn(move || {
+            let _ = sound_bank_ref.set(SoundBank::load(&sound_directory, format));
+        });
+
+        player
+    }
+}
+
+impl Player for SoundPlayer {
+    fn play(&mut self, sound: SoundId, options: PlayOptions) {
+        let engine = match &mut self.engine {
+            Some(engine) => engine,
+            None => return,
+        };
+        match self.sound_bank.get().and_then(|bank| bank.get(sound)) {
+            Some(loaded_sound) => engine.play(sound, loaded_sound.clone(), options),
+            None => eprintln!("Sound {:?} is not loaded.", sound),
+        }
+    }
+
+    fn stop(&mut self, sound: SoundId) {
+        if let Some(engine) = &mut self.engine {
+            engine.stop(sound);
+        }
+    }
+
+    fn stop_all(&mut self) {
+        if let Some(engine) = &mut self.engine {
+            engine.stop_all();
+        }
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        if let Some(engine) = &mut self.engine {
+            engine.set_volume(volume);
+        }
+    }
+
+    fn start_ticking(&mut self, ticking: Ticking) {
+        let engine = match &mut self.engine {
+            Some(engine) => engine,
+            None => return,
+        };
+        let sounds = self
+            .sound_bank
+            .get()
+            .and_then(|bank| Some((bank.get(SoundId::Tick)?, bank.get(SoundId::Tock)?)));
+        match sounds {
+            Some((tick, tock)) => engine.start_ticking(tick.clone(), tock.clone(), ticking),
+            None => eprintln!("Tick sounds are not loaded."),
+        }
+    }
+
+    fn stop_ticking(&mut self) {
+        if let Some(engine) = &mut self.engine {
+            engine.stop_ticking();
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.sound_bank.get().is_some()
+    }
+
+    fn is_playing(&self) -> bool {
+        self.engine
+            .as_ref()
+            .is_some_and(|engine| engine.is_playing())
+    }
+
+    fn update(&mut self) {
+        if let Some(engine) = &mut self.engine {
+            engine.update();
+        }
+    }
+}
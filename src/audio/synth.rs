@@ -0,0 +1,278 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use crate::audio::sound::Sound;
+
+pub const DEFAULT_TEMPO_BPM: u32 = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+}
+
+impl Waveform {
+    // Value of the waveform at the given phase in the range 0.0..1.0.
+    fn value(&self, phase: f32) -> f32 {
+        match self {
+            Waveform::Sine => f32::sin(2f32 * PI * phase),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1f32
+                } else {
+                    -1f32
+                }
+            }
+            Waveform::Triangle => 1f32 - 4f32 * f32::abs(phase - 0.5),
+        }
+    }
+}
+
+/// Attack, decay, sustain and release of a single note. The release is
+/// part of the note duration, so consecutive notes do not overlap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: Duration,
+    pub decay: Duration,
+    pub sustain: f32,
+    pub release: Duration,
+}
+
+impl Envelope {
+    fn amplitude(&self, time: f32, note_length: f32) -> f32 {
+        let attack = self.attack.as_secs_f32();
+        let decay = self.decay.as_secs_f32();
+        let release = f32::min(self.release.as_secs_f32(), note_length);
+        let release_start = note_length - release;
+
+        let level = if time < attack {
+            time / attack
+        } else if time < attack + decay {
+            1f32 - (1f32 - self.sustain) * (time - attack) / decay
+        } else {
+            self.sustain
+        };
+
+        if time >= release_start && release > 0f32 {
+            level * f32::max(0f32, (note_length - time) / release)
+        } else {
+            level
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instrument {
+    pub waveform: Waveform,
+    pub envelope: Envelope,
+    pub volume: f32,
+}
+
+impl Instrument {
+    pub fn chime() -> Self {
+        Instrument {
+            waveform: Waveform::Sine,
+            envelope: Envelope {
+                attack: Duration::from_millis(5),
+                decay: Duration::from_millis(80),
+                sustain: 0.6,
+                release: Duration::from_millis(60),
+            },
+            volume: 0.8,
+        }
+    }
+
+    pub fn beep() -> Self {
+        Instrument {
+            waveform: Waveform::Square,
+            envelope: Envelope {
+                attack: Duration::from_millis(2),
+                decay: Duration::from_millis(10),
+                sustain: 0.8,
+                release: Duration::from_millis(10),
+            },
+            volume: 0.4,
+        }
+    }
+
+    pub fn click() -> Self {
+        Instrument {
+            waveform: Waveform::Triangle,
+            envelope: Envelope {
+                attack: Duration::from_millis(1),
+                decay: Duration::from_millis(15),
+                sustain: 0.1,
+                release: Duration::from_millis(10),
+            },
+            volume: 0.6,
+        }
+    }
+}
+
+/// A note of a melody, a frequency of None is a rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note {
+    pub frequency: Option<f32>,
+    pub duration: Duration,
+}
+
+fn parse_pitch(pitch: &str) -> Result<Option<f32>, String> {
+    if pitch == "R" {
+        return Ok(None);
+    }
+
+    let mut chars = pitch.chars();
+    let mut semitone: i32 = match chars.next() {
+        Some('C') => 0,
+        Some('D') => 2,
+        Some('E') => 4,
+        Some('F') => 5,
+        Some('G') => 7,
+        Some('A') => 9,
+        Some('B') => 11,
+        _ => return Err(format!("Unknown note name in \"{}\".", pitch)),
+    };
+    let mut octave_text = chars.as_str();
+    if let Some(rest) = octave_text.strip_prefix('#') {
+        semitone += 1;
+        octave_text = rest;
+    } else if let Some(rest) = octave_text.strip_prefix('b') {
+        semitone -= 1;
+        octave_text = rest;
+    }
+    // Octaves beyond 9 are inaudible and would overflow the note number.
+    let octave = octave_text
+        .parse::<i32>()
+        .ok()
+        .filter(|octave| (0..=9).contains(octave))
+        .ok_or(format!("Invalid octave in \"{}\".", pitch))?;
+
+    // MIDI note number 69 is A4 at 440 Hz.
+    let midi_note = (octave + 1) * 12 + semitone;
+    Ok(Some(440f32 * f32::powf(2f32, (midi_note - 69) as f32 / 12f32)))
+}
+
+fn parse_note_value(value: &str, tempo_bpm: u32) -> Result<Duration, String> {
+    let (value, factor) = match value.strip_suffix('.') {
+        Some(value) => (value, 1.5f32),
+        None => (value, 1f32),
+    };
+    let value = value
+        .parse::<u32>()
+        .ok()
+        .filter(|value| *value > 0)
+        .ok_or(format!("Invalid note value \"{}\".", value))?;
+    let beat_secs = 60f32 / tempo_bpm as f32;
+    Ok(Duration::from_secs_f32(beat_secs * 4f32 / value as f32 * factor))
+}
+
+/// Parses a melody like "C5:8 E5:8 G5:4 R:4 C#6:4.". Each note is a pitch
+/// with optional sharp (#) or flat (b), or R for a rest, followed by the
+/// note value: 4 is a quarter note, 8 an eighth and so on, a trailing dot
+/// makes it a dotted note.
+pub fn parse_melody(melody: &str, tempo_bpm: u32) -> Result<Vec<Note>, String> {
+    melody
+        .split_whitespace()
+        .map(|note| {
+            let (pitch, value) = note
+                .split_once(':')
+                .ok_or(format!("Missing note value in \"{}\".", note))?;
+            Ok(Note {
+                frequency: parse_pitch(pitch)?,
+                duration: parse_note_value(value, tempo_bpm)?,
+            })
+        })
+        .collect()
+}
+
+pub fn render_melody(notes: &[Note], instrument: &Instrument, sample_rate: u32) -> Sound {
+    let mut samples = Vec::new();
+    for note in notes {
+        let frame_count = (note.duration.as_secs_f32() * sample_rate as f32) as usize;
+        let note_length = note.duration.as_secs_f32();
+        for frame in 0..frame_count {
+            let time = frame as f32 / sample_rate as f32;
+            let sample = match note.frequency {
+                Some(frequency) => {
+                    let phase = f32::fract(time * frequency);
+                    instrument.waveform.value(phase)
+                        * instrument.envelope.amplitude(time, note_length)
+                        * instrument.volume
+                }
+                None => 0f32,
+            };
+            samples.push(sample);
+        }
+    }
+    Sound::new(samples, 1, sample_rate)
+}
+
+pub fn render_tone(
+    frequency: f32,
+    duration: Duration,
+    instrument: &Instrument,
+    sample_rate: u32,
+) -> Sound {
+    let note = Note {
+        frequency: Some(frequency),
+        duration,
+    };
+    render_melody(&[note], instrument, sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_melody() {
+        let notes = parse_melody("A4:4 C5:8 R:2 A#4:4.", 120).unwrap();
+
+        assert_eq!(notes.len(), 4);
+        assert_eq!(notes[0].frequency, Some(440f32));
+        assert_eq!(notes[0].duration, Duration::from_millis(500));
+        assert!(f32::abs(notes[1].frequency.unwrap() - 523.25) < 0.01);
+        assert_eq!(notes[1].duration, Duration::from_millis(250));
+        assert_eq!(notes[2].frequency, None);
+        assert_eq!(notes[2].duration, Duration::from_secs(1));
+        assert!(f32::abs(notes[3].frequency.unwrap() - 466.16) < 0.01);
+        assert_eq!(notes[3].duration, Duration::from_millis(750));
+    }
+
+    #[test]
+    fn test_parse_melody_errors() {
+        assert!(parse_melody("H4:4", 120).is_err());
+        assert!(parse_melody("C:4", 120).is_err());
+        assert!(parse_melody("C10:4", 120).is_err());
+        assert!(parse_melody("C999999999:4", 120).is_err());
+        assert!(parse_melody("C-1:4", 120).is_err());
+        assert!(parse_melody("C4", 120).is_err());
+        assert!(parse_melody("C4:0", 120).is_err());
+    }
+
+    #[test]
+    fn test_render_melody_length_and_rests() {
+        let notes = parse_melody("A4:4 R:4", 120).unwrap();
+
+        let sound = render_melody(&notes, &Instrument::chime(), 1000);
+
+        assert_eq!(sound.frame_count(), 1000);
+        assert!((0..500).any(|frame| sound.sample(frame, 0) != 0f32));
+        assert!((500..1000).all(|frame| sound.sample(frame, 0) == 0f32));
+    }
+
+    #[test]
+    fn test_envelope_fades_in_and_out() {
+        let sound = render_tone(
+            250f32,
+            Duration::from_millis(500),
+            &Instrument::beep(),
+            1000,
+        );
+
+        assert_eq!(sound.sample(0, 0), 0f32);
+        assert!(f32::abs(sound.sample(100, 0)) > 0.3);
+        assert!(f32::abs(sound.sample(499, 0)) < 0.1);
+    }
+}
@@ -14,7 +14,6 @@ pub struct WavFile {
     bytes_per_sec: u32,
     bytes_per_sample: u16,
     bits_per_sample: u16,
-    current_sample: usize,
     bytes: Vec<u8>,
     data_chunk_offset: usize,
 }
@@ -70,7 +69,10 @@ impl WavFile {
         if n != bytes.len() {
             println!("Warning, not all metadata bytes could be read.")
         }
+        WavFile::from_bytes(file_name, bytes)
+    }
 
+    pub fn from_bytes(file_name: String, bytes: Vec<u8>) -> Self {
         let riff_string = read_string(&bytes, 0, 4);
         let file_byte_size = read_u32_value(&bytes, 4);
 
@@ -99,7 +101,7 @@ impl WavFile {
 
         WavFile {
             is_valid: riff_string == "RIFF"
-                && file_byte_size as u64 == metadata.len()
+                && file_byte_size as usize == bytes.len()
                 && wave_string == "WAVE"
                 && fmt_string == "fmt "
                 && fmt_byte_size == 16
@@ -114,7 +116,6 @@ impl WavFile {
             bytes_per_sec,
             bytes_per_sample,
             bits_per_sample,
-            current_sample: 0,
             bytes,
             data_chunk_offset,
         }
@@ -132,7 +133,6 @@ impl WavFile {
             bytes_per_sec: 0,
             bytes_per_sample: 0,
             bits_per_sample: 0,
-            current_sample: 0,
             bytes: vec![],
             data_chunk_offset: 0,
         }
@@ -146,20 +146,23 @@ impl WavFile {
         self.channel_count
     }
 
-    pub fn rewind(&mut self) {
-        self.current_sample = 0;
+    pub fn frame_count(&self) -> usize {
+        let frame_byte_size = 2 * self.channel_count as usize;
+        if frame_byte_size == 0 {
+            return 0;
+        }
+        self.bytes.len().saturating_sub(self.data_chunk_offset) / frame_byte_size
     }
 
-    pub fn get_samples(&mut self, data: &mut [f32], volume_factor: f32) {
-        for data_out in data.iter_mut() {
-            let sample_data_index = self.data_chunk_offset + 2 * self.current_sample;
-            if sample_data_index + 1 < self.bytes.len() {
-                let sample = read_i16_value(&self.bytes, sample_data_index);
-                *data_out = (sample as f32 / 32768f32) * volume_factor;
-                self.current_sample += 1;
-            } else {
-                *data_out = 0f32;
-            }
+    /// Returns the sample of the given channel in the given frame,
+    /// normalized to the range -1.0..1.0.
+    pub fn sample(&self, frame: usize, channel: usize) -> f32 {
+        let sample_index = frame * self.channel_count as usize + channel;
+        let sample_data_index = self.data_chunk_offset + 2 * sample_index;
+        if sample_data_index + 1 < self.bytes.len() {
+            read_i16_value(&self.bytes, sample_data_index) as f32 / 32768f32
+        } else {
+            0f32
         }
     }
 }
@@ -176,7 +179,6 @@ impl fmt::Debug for WavFile {
             .field("bytes_per_sec", &self.bytes_per_sec)
             .field("bytes_per_sample", &self.bytes_per_sample)
             .field("bits_per_sample", &self.bits_per_sample)
-            .field("current_sample", &self.current_sample)
             .field("bytes (count)", &self.bytes.len())
             .finish()
     }
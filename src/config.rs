@@ -0,0 +1,263 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alarm::{AlarmProfile, DEFAULT_ALARM_PROFILE};
+use crate::ambient::{AmbientSchedule, DisplaySettings};
+use crate::gui::clock_face::ClockFace;
+use crate::gui::display_control::Orientation;
+use crate::gui::display_model::DisplayModel;
+use crate::gui::render_scheduler::RenderSettings;
+use crate::hourglass::{default_warnings, HourglassState, TickSettings, Warning};
+use crate::idle::IdleSettings;
+use crate::program::Program;
+use crate::quiet_hours::QuietHours;
+use crate::schedule::ScheduleRule;
+
+pub const CONFIG_PATH: &str = "./hourglass.json";
+
+/// A named timer duration, optionally with its own face and alarm profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub duration_ms: u128,
+    #[serde(default)]
+    pub clock_face: Option<ClockFace>,
+    #[serde(default)]
+    pub alarm_profile: Option<String>,
+}
+
+impl Preset {
+    pub fn new(name: &str, duration_ms: u128) -> Self {
+        Preset {
+            name: name.to_string(),
+            duration_ms,
+            clock_face: None,
+            alarm_profile: None,
+        }
+    }
+}
+
+fn default_alarm_profile() -> String {
+    DEFAULT_ALARM_PROFILE.to_string()
+}
+
+fn default_clock_face() -> ClockFace {
+    ClockFace::Blocks
+}
+
+fn default_programs() -> Vec<Program> {
+    vec![Program::pomodoro()]
+}
+
+pub fn default_presets() -> Vec<Preset> {
+    vec![
+        Preset::new("Screen time", 20 * 60 * 1000),
+        Preset::new("Teeth", 2 * 60 * 1000),
+        Preset::new("Homework", 45 * 60 * 1000),
+    ]
+}
+
+/// Settings kept on the device across restarts. Alarm profiles only lists
+/// the user defined ones, the built-in profiles are always available. The
+/// selected alarm profile may be either.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default = "default_presets")]
+    pub presets: Vec<Preset>,
+    #[serde(default)]
+    pub alarm_profiles: Vec<AlarmProfile>,
+    #[serde(default = "default_alarm_profile")]
+    pub alarm_profile: String,
+    #[serde(default = "default_programs")]
+    pub programs: Vec<Program>,
+    #[serde(default)]
+    pub schedules: Vec<ScheduleRule>,
+    #[serde(default = "TickSettings::new")]
+    pub tick: TickSettings,
+    #[serde(default = "default_warnings")]
+    pub warnings: Vec<Warning>,
+    #[serde(default = "default_clock_face")]
+    pub clock_face: ClockFace,
+    #[serde(default = "QuietHours::new")]
+    pub quiet_hours: QuietHours,
+    #[serde(default = "IdleSettings::new")]
+    pub idle: IdleSettings,
+    #[serde(default = "DisplaySettings::new")]
+    pub display: DisplaySettings,
+    #[serde(default = "Orientation::new")]
+    pub orientation: Orientation,
+    #[serde(default = "DisplayModel::new")]
+    pub display_model: DisplayModel,
+    #[serde(default = "AmbientSchedule::new")]
+    pub ambient: AmbientSchedule,
+    #[serde(default = "RenderSettings::new")]
+    pub render: RenderSettings,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config {
+            presets: default_presets(),
+            alarm_profiles: Vec::new(),
+            alarm_profile: default_alarm_profile(),
+            programs: default_programs(),
+            schedules: Vec::new(),
+            tick: TickSettings::new(),
+            warnings: default_warnings(),
+            clock_face: default_clock_face(),
+            quiet_hours: QuietHours::new(),
+            idle: IdleSettings::new(),
+            display: DisplaySettings::new(),
+            orientation: Orientation::new(),
+            display_model: DisplayModel::new(),
+            ambient: AmbientSchedule::new(),
+            render: RenderSettings::new(),
+        }
+    }
+
+    /// Reads the config, a missing or broken file gives the defaults.
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(text) => Config::from_json(&text).unwrap_or_else(|error| {
+                eprintln!("Unable to parse config {}: {}", path, error);
+                Config::new()
+            }),
+            Err(_) => Config::new(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|error| error.to_string())?;
+        fs::write(path, text).map_err(|error| error.to_string())
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|error| error.to_string())
+    }
+
+    pub fn from_state(state: &HourglassState) -> Self {
+        let built_in_names: Vec<String> = AlarmProfile::built_in()
+            .into_iter()
+            .map(|profile| profile.name)
+            .collect();
+        Config {
+            presets: state.presets.clone(),
+            alarm_profiles: state
+                .alarm_profiles
+                .iter()
+                .filter(|profile| !built_in_names.contains(&profile.name))
+                .cloned()
+                .collect(),
+            alarm_profile: state.alarm_profile.name.clone(),
+            programs: state.programs.clone(),
+            schedules: state.schedules.clone(),
+            tick: state.tick_settings,
+            warnings: state.warnings.clone(),
+            clock_face: state.clock_face,
+            quiet_hours: state.quiet_hours,
+            idle: state.idle,
+            display: state.display,
+            orientation: state.orientation,
+            display_model: state.display_model,
+            ambient: state.ambient.clone(),
+            render: state.render,
+        }
+    }
+
+    pub fn apply_to(self, state: &mut HourglassState) {
+        state.presets = self.presets;
+        state.programs = self.programs;
+        state.schedules = self.schedules;
+        state.tick_settings = self.tick.sanitized();
+        state.warnings = self.warnings;
+        state.clock_face = self.clock_face;
+        state.quiet_hours = self.quiet_hours;
+        state.idle = self.idle;
+        state.display = self.display;
+        state.orientation = self.orientation;
+        state.display_model = self.display_model;
+        state.ambient = self.ambient;
+        state.render = self.render;
+        for profile in self.alarm_profiles {
+            state
+                .alarm_profiles
+                .retain(|known| known.name != profile.name);
+            state.alarm_profiles.push(profile);
+        }
+        if !state.select_alarm_profile(&self.alarm_profile) {
+            eprintln!("Unknown alarm profile {}, using the default.", self.alarm_profile);
+        }
+    }
+}
+
+/// Saves the settings of the state, errors are only logged.
+pub fn save_config(state: &HourglassState) {
+    if let Err(error) = Config::from_state(state).save(CONFIG_PATH) {
+        eprintln!("Unable to save config {}: {}", CONFIG_PATH, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_config_has_default_presets() {
+        let config = Config::from_json("{}").unwrap();
+
+        assert_eq!(config.presets, default_presets());
+        assert_eq!(config.programs, default_programs());
+        assert!(config.alarm_profiles.is_empty());
+        assert!(config.schedules.is_empty());
+    }
+
+    #[test]
+    fn test_config_round_trip_through_the_state() {
+        let mut config = Config::new();
+        config.presets[1].clock_face = Some(ClockFace::Digits);
+        config.presets[1].alarm_profile = Some("quiet".to_string());
+        let mut quiet = AlarmProfile::gentle();
+        quiet.name = "quiet".to_string();
+        quiet.sound = None;
+        config.alarm_profiles.push(quiet);
+        config.alarm_profile = "quiet".to_string();
+        config.schedules.push(ScheduleRule::new(
+            "Evening",
+            crate::schedule::parse_weekdays("weekdays").unwrap(),
+            crate::schedule::parse_time("18:30").unwrap(),
+            "Screen time",
+            chrono::NaiveDateTime::default(),
+        ));
+        config.tick.enabled = true;
+        config.warnings.pop();
+        config.clock_face = ClockFace::Pie;
+        config.quiet_hours.enabled = true;
+        config.render.max_fps = 5;
+
+        let mut state = HourglassState::new();
+        config.clone().apply_to(&mut state);
+        assert_eq!(state.alarm_profile.name, "quiet");
+
+        let text = serde_json::to_string(&Config::from_state(&state)).unwrap();
+        assert_eq!(Config::from_json(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn test_tick_settings_are_limited() {
+        let config =
+            Config::from_json(r#"{"tick": {"enabled": true, "interval_s": 0, "volume": 2.0}}"#)
+                .unwrap();
+
+        let mut state = HourglassState::new();
+        config.apply_to(&mut state);
+        assert_eq!(state.tick_settings.interval_s, 1);
+        assert_eq!(state.tick_settings.volume, 1f32);
+        assert!(state.tick_settings.enabled);
+    }
+
+    #[test]
+    fn test_broken_config_is_an_error() {
+        assert!(Config::from_json("{\"presets\": 3}").is_err());
+    }
+}
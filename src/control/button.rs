@@ -0,0 +1,146 @@
+#[cfg(target_arch = "arm")]
+use crate::gui::render_scheduler::RenderWaker;
+#[cfg(target_arch = "arm")]
+use rppal::gpio::{Gpio, InputPin, Trigger};
+
+const BUTTON_GPIO: u8 = 17;
+const LONG_PRESS_MS: u128 = 1000;
+
+#[cfg_attr(test, mockall::automock)]
+pub trait ButtonPinInterface {
+    fn is_pressed(&self) -> bool;
+}
+
+#[cfg(target_arch = "arm")]
+impl ButtonPinInterface for InputPin {
+    // The button connects the pin to ground, the pull-up keeps it high otherwise.
+    fn is_pressed(&self) -> bool {
+        self.is_low()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonPress {
+    Short,
+    Long,
+}
+
+/// Polled push button. A long press is reported as soon as it is held for
+/// LONG_PRESS_MS, a short press when it is released before that.
+/// On the Pi the edges of the pin wake the loop for the poll.
+pub struct Button<T> {
+    pin: T,
+    pressed_since_ms: Option<u128>,
+    long_press_reported: bool,
+}
+
+#[cfg(target_arch = "arm")]
+impl Button<InputPin> {
+    pub fn new(waker: RenderWaker) -> Button<InputPin> {
+        let mut pin = Gpio::new()
+            .unwrap()
+            .get(BUTTON_GPIO)
+            .unwrap()
+            .into_input_pullup();
+        // The interrupt lasts as long as the button owns the pin.
+        pin.set_async_interrupt(Trigger::Both, move |_| waker.wake())
+            .unwrap();
+        Button::new_generic(pin)
+    }
+}
+
+impl<T: ButtonPinInterface> Button<T> {
+    pub fn new_generic(pin: T) -> Button<T> {
+        Button {
+            pin,
+            pressed_since_ms: None,
+            long_press_reported: false,
+        }
+    }
+
+    pub fn poll(&mut self, current_time_ms: u128) -> Option<ButtonPress> {
+        match (self.pin.is_pressed(), self.pressed_since_ms) {
+            (true, None) => {
+                self.pressed_since_ms = Some(current_time_ms);
+                self.long_press_reported = false;
+                None
+            }
+            (true, Some(pressed_since_ms)) => {
+                // The clock may be stepped back while the button is held.
+                let held_ms = current_time_ms.saturating_sub(pressed_since_ms);
+                if !self.long_press_reported && held_ms >= LONG_PRESS_MS {
+                    self.long_press_reported = true;
+                    Some(ButtonPress::Long)
+                } else {
+                    None
+                }
+            }
+            (false, Some(_)) => {
+                self.pressed_since_ms = None;
+                (!self.long_press_reported).then_some(ButtonPress::Short)
+            }
+            (false, None) => None,
+        }
+    }
+
+    /// The time a held button turns into a long press, the loop has to
+    /// poll then without an edge.
+    pub fn long_press_due_ms(&self) -> Option<u128> {
+        match (self.pressed_since_ms, self.long_press_reported) {
+            (Some(pressed_since_ms), false) => Some(pressed_since_ms + LONG_PRESS_MS),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockall::*;
+
+    fn get_new_mocked_button(pin_states: &[bool]) -> Button<MockButtonPinInterface> {
+        let mut pin = MockButtonPinInterface::new();
+        let mut sequence = Sequence::new();
+        for pin_state in pin_states {
+            pin.expect_is_pressed()
+                .return_const(*pin_state)
+                .times(1)
+                .in_sequence(&mut sequence);
+        }
+        Button::new_generic(pin)
+    }
+
+    #[test]
+    fn test_short_press_is_reported_on_release() {
+        let mut button = get_new_mocked_button(&[false, true, true, false, false]);
+
+        assert_eq!(button.poll(0), None);
+        assert_eq!(button.poll(250), None);
+        assert_eq!(button.poll(500), None);
+        assert_eq!(button.poll(750), Some(ButtonPress::Short));
+        assert_eq!(button.poll(1000), None);
+    }
+
+    #[test]
+    fn test_long_press_is_reported_once_while_held() {
+        let mut button = get_new_mocked_button(&[true, true, true, true, false]);
+
+        assert_eq!(button.long_press_due_ms(), None);
+        assert_eq!(button.poll(0), None);
+        assert_eq!(button.long_press_due_ms(), Some(LONG_PRESS_MS));
+        assert_eq!(button.poll(750), None);
+        assert_eq!(button.poll(1000), Some(ButtonPress::Long));
+        assert_eq!(button.long_press_due_ms(), None);
+        assert_eq!(button.poll(1250), None);
+        assert_eq!(button.poll(1500), None);
+    }
+
+    #[test]
+    fn test_clock_stepped_back_is_no_long_press() {
+        let mut button = get_new_mocked_button(&[true, true, false]);
+
+        assert_eq!(button.poll(10_000), None);
+        assert_eq!(button.poll(2_000), None);
+        assert_eq!(button.poll(2_250), Some(ButtonPress::Short));
+    }
+}
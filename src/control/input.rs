@@ -1,15 +1,78 @@
+use crate::gui::render_scheduler::RenderWaker;
 use std::io;
-use std::io::Read;
+use std::io::BufRead;
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
-use std::{thread, time};
+use std::thread;
 
-pub fn spawn_await_input_enter_thread() -> (thread::JoinHandle<()>, mpsc::Receiver<()>) {
-    let (tx, rx): (Sender<()>, Receiver<()>) = mpsc::channel();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleCommand {
+    Quit,
+    AcknowledgeAlarm,
+    Snooze,
+    ListPresets,
+    StartPreset(usize),
+}
+
+pub const CONSOLE_HELP: &str = "Press Enter to end, a + Enter to acknowledge the alarm, \
+s + Enter to snooze, p + Enter to list the presets and p <number> + Enter to start one...";
+
+fn parse_console_command(line: &str) -> Option<ConsoleCommand> {
+    match line.trim() {
+        "" | "q" => Some(ConsoleCommand::Quit),
+        "a" => Some(ConsoleCommand::AcknowledgeAlarm),
+        "s" => Some(ConsoleCommand::Snooze),
+        "p" => Some(ConsoleCommand::ListPresets),
+        line => {
+            let number = line.strip_prefix('p')?.trim().parse::<usize>().ok()?;
+            Some(ConsoleCommand::StartPreset(number))
+        }
+    }
+}
+
+/// Each command wakes the render loop to handle it.
+pub fn spawn_console_input_thread(
+    waker: RenderWaker,
+) -> (thread::JoinHandle<()>, mpsc::Receiver<ConsoleCommand>) {
+    let (tx, rx): (Sender<ConsoleCommand>, Receiver<ConsoleCommand>) = mpsc::channel();
     let input_thread = thread::spawn(move || {
-        let mut buffer = [0u8; 1];
-        io::stdin().read_exact(&mut buffer).unwrap();
-        tx.send(()).unwrap();
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            match parse_console_command(&line) {
+                Some(command) => {
+                    if tx.send(command).is_err() {
+                        break;
+                    }
+                    waker.wake();
+                    if command == ConsoleCommand::Quit {
+                        break;
+                    }
+                }
+                None => println!("{}", CONSOLE_HELP),
+            }
+        }
     });
     (input_thread, rx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_console_command() {
+        assert_eq!(parse_console_command(""), Some(ConsoleCommand::Quit));
+        assert_eq!(
+            parse_console_command("a\r"),
+            Some(ConsoleCommand::AcknowledgeAlarm)
+        );
+        assert_eq!(parse_console_command(" s "), Some(ConsoleCommand::Snooze));
+        assert_eq!(parse_console_command("p"), Some(ConsoleCommand::ListPresets));
+        assert_eq!(parse_console_command("p 2"), Some(ConsoleCommand::StartPreset(2)));
+        assert_eq!(parse_console_command("p x"), None);
+        assert_eq!(parse_console_command("x"), None);
+    }
+}
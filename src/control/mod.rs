@@ -1,2 +1,3 @@
+pub mod button;
 pub mod input;
 pub mod webservice;
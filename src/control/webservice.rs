@@ -1,21 +1,60 @@
-use crate::hourglass::{HourglassState, ThreadSafeHourglassState, MAXIMUM_DURATION_MS};
+use crate::alarm::{AlarmPattern, AlarmProfile, AlarmSound, Escalation};
+use crate::ambient::AmbientStep;
+use crate::audio::sound_bank::SoundId;
+use crate::config::{save_config, Preset};
+use crate::gui::clock_face::ClockFace;
+use crate::gui::display_control::{Brightness, Orientation, Rotation};
+use crate::gui::render_scheduler::{RenderWaker, MAX_FPS_LIMIT};
+use crate::hourglass::{
+    format_warnings, now_ms, parse_warnings, HourglassEvent, HourglassState,
+    ThreadSafeHourglassState, TimerMode, MAXIMUM_DURATION_MS, MAXIMUM_TICK_INTERVAL_S,
+};
+use crate::quiet_hours::{NightDisplay, QuietHours};
+use crate::schedule::{parse_time, parse_weekdays, ScheduleRule};
 use actix_files::Files;
-use actix_web::dev::Server;
+use chrono::NaiveTime;
+use actix_web::dev::{Server, Service};
 use actix_web::{rt::System, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, RwLock};
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::str::FromStr;
 use std::thread;
 use std::time::SystemTime;
 
-pub fn start_webservice(state: ThreadSafeHourglassState) {
+pub fn start_webservice(state: ThreadSafeHourglassState, waker: RenderWaker) {
     thread::spawn(move || {
         let server = HttpServer::new(move || {
+            let activity_state = state.clone();
+            let waker = waker.clone();
             App::new()
                 .app_data(web::Data::new(state.clone()))
+                // Commands wake the idle screen and the render loop once they
+                // are done, the polled queries do not.
+                .wrap_fn(move |req, srv| {
+                    let is_command = !req.path().starts_with("/get_");
+                    if is_command {
+                        activity_state.write().unwrap().touch(now_ms());
+                    }
+                    let response = srv.call(req);
+                    let waker = waker.clone();
+                    async move {
+                        let response = response.await;
+                        if is_command {
+                            waker.wake();
+                        }
+                        response
+                    }
+                })
                 .route("/", web::get().to(index))
                 .route("/start", web::get().to(start))
                 .route("/stop", web::get().to(stop))
+                .route("/pause", web::get().to(pause))
+                .route("/resume", web::get().to(resume))
+                .route("/acknowledge_alarm", web::get().to(acknowledge_alarm))
+                .route("/snooze", web::get().to(snooze))
                 .route("/minus_minute", web::get().to(minus_minute))
                 .route("/plus_minute", web::get().to(plus_minute))
                 .route("/get_ticking", web::get().to(get_ticking))
@@ -25,6 +64,157 @@ pub fn start_webservice(state: ThreadSafeHourglassState) {
                     web::get().to(set_duration_ms),
                 )
                 .route("/get_target_time_ms", web::get().to(get_target_time_ms))
+                .route("/get_paused", web::get().to(get_paused))
+                .route("/get_last_alarm", web::get().to(get_last_alarm))
+                .route("/get_alarm_profiles", web::get().to(get_alarm_profiles))
+                .route("/get_alarm_profile", web::get().to(get_alarm_profile))
+                .route(
+                    "/set_alarm_profile/{name}",
+                    web::get().to(set_alarm_profile),
+                )
+                .route(
+                    "/set_alarm_profile_visual/{name}/{pattern}/{interval_ms}/{inverted}",
+                    web::get().to(set_alarm_profile_visual),
+                )
+                .route(
+                    "/set_alarm_profile_message/{name}/{message}",
+                    web::get().to(set_alarm_profile_message),
+                )
+                .route(
+                    "/set_alarm_profile_sound/{name}/{sound}/{gap_ms}/{volume_percent}",
+                    web::get().to(set_alarm_profile_sound),
+                )
+                .route(
+                    "/delete_alarm_profile_sound/{name}",
+                    web::get().to(delete_alarm_profile_sound),
+                )
+                .route(
+                    concat!(
+                        "/set_alarm_profile_escalation/{name}/{after_ms}/{interval_ms}",
+                        "/{sound_gap_ms}/{volume_percent}"
+                    ),
+                    web::get().to(set_alarm_profile_escalation),
+                )
+                .route(
+                    "/delete_alarm_profile_escalation/{name}",
+                    web::get().to(delete_alarm_profile_escalation),
+                )
+                .route(
+                    "/set_alarm_profile_duration/{name}/{duration_ms}",
+                    web::get().to(set_alarm_profile_duration),
+                )
+                .route(
+                    "/delete_alarm_profile/{name}",
+                    web::get().to(delete_alarm_profile),
+                )
+                .route("/get_warnings", web::get().to(get_warnings))
+                .route("/set_warnings/{warnings}", web::get().to(set_warnings))
+                .route("/get_tick_sound", web::get().to(get_tick_sound))
+                .route(
+                    "/set_tick_sound/{enabled}",
+                    web::get().to(set_tick_sound),
+                )
+                .route(
+                    "/set_tick_interval_s/{interval_s}",
+                    web::get().to(set_tick_interval_s),
+                )
+                .route(
+                    "/set_tick_volume_percent/{volume_percent}",
+                    web::get().to(set_tick_volume_percent),
+                )
+                .route("/get_clock_face", web::get().to(get_clock_face))
+                .route("/set_clock_face/{face}", web::get().to(set_clock_face))
+                .route("/get_presets", web::get().to(get_presets))
+                .route(
+                    "/set_preset/{name}/{duration_ms}",
+                    web::get().to(set_preset),
+                )
+                .route(
+                    "/set_preset_clock_face/{name}/{face}",
+                    web::get().to(set_preset_clock_face),
+                )
+                .route(
+                    "/set_preset_alarm_profile/{name}/{alarm_profile}",
+                    web::get().to(set_preset_alarm_profile),
+                )
+                .route("/delete_preset/{name}", web::get().to(delete_preset))
+                .route("/start_preset/{name}", web::get().to(start_preset))
+                .route("/get_programs", web::get().to(get_programs))
+                .route("/start_program/{name}", web::get().to(start_program))
+                .route("/get_program_progress", web::get().to(get_program_progress))
+                .route("/start_stopwatch", web::get().to(start_stopwatch))
+                .route("/lap", web::get().to(lap))
+                .route("/reset_stopwatch", web::get().to(reset_stopwatch))
+                .route("/get_mode", web::get().to(get_mode))
+                .route("/get_elapsed_ms", web::get().to(get_elapsed_ms))
+                .route("/get_laps", web::get().to(get_laps))
+                .route("/get_schedules", web::get().to(get_schedules))
+                .route(
+                    "/set_schedule/{name}/{weekdays}/{time}/{preset}",
+                    web::get().to(set_schedule),
+                )
+                .route(
+                    "/set_schedule_enabled/{name}/{enabled}",
+                    web::get().to(set_schedule_enabled),
+                )
+                .route("/delete_schedule/{name}", web::get().to(delete_schedule))
+                .route("/get_quiet_hours", web::get().to(get_quiet_hours))
+                .route("/set_quiet_hours/{start}/{end}", web::get().to(set_quiet_hours))
+                .route(
+                    "/set_quiet_hours_enabled/{enabled}",
+                    web::get().to(set_quiet_hours_enabled),
+                )
+                .route(
+                    "/set_quiet_hours_display/{display}",
+                    web::get().to(set_quiet_hours_display),
+                )
+                .route(
+                    "/set_quiet_hours_volume_percent/{volume_percent}",
+                    web::get().to(set_quiet_hours_volume_percent),
+                )
+                .route(
+                    "/set_quiet_hours_contrast/{contrast}",
+                    web::get().to(set_quiet_hours_contrast),
+                )
+                .route("/get_display_settings", web::get().to(get_display_settings))
+                .route(
+                    "/set_display_brightness/{brightness}",
+                    web::get().to(set_display_brightness),
+                )
+                .route(
+                    "/set_display_contrast/{contrast}",
+                    web::get().to(set_display_contrast),
+                )
+                .route(
+                    "/set_display_inverted/{inverted}",
+                    web::get().to(set_display_inverted),
+                )
+                .route("/get_orientation", web::get().to(get_orientation))
+                .route(
+                    "/set_orientation/{rotation}/{mirrored}",
+                    web::get().to(set_orientation),
+                )
+                .route("/get_render_settings", web::get().to(get_render_settings))
+                .route("/set_max_fps/{max_fps}", web::get().to(set_max_fps))
+                .route(
+                    "/set_transitions/{transitions}",
+                    web::get().to(set_transitions),
+                )
+                .route("/get_ambient_schedule", web::get().to(get_ambient_schedule))
+                .route(
+                    "/set_ambient_schedule_enabled/{enabled}",
+                    web::get().to(set_ambient_schedule_enabled),
+                )
+                .route(
+                    "/set_ambient_step/{start}/{brightness}/{contrast}",
+                    web::get().to(set_ambient_step),
+                )
+                .route("/delete_ambient_step/{start}", web::get().to(delete_ambient_step))
+                .route("/get_idle_settings", web::get().to(get_idle_settings))
+                .route(
+                    "/set_idle_timeouts/{shift_after_s}/{screensaver_after_s}/{off_after_s}",
+                    web::get().to(set_idle_timeouts),
+                )
                 .route("/end_service", web::get().to(end_service))
                 .service(Files::new("/", "./html/"))
         })
@@ -36,24 +226,16 @@ pub fn start_webservice(state: ThreadSafeHourglassState) {
     });
 }
 
-fn start_hourglass_timer(data: &web::Data<ThreadSafeHourglassState>) {
+fn start_hourglass_timer(data: &web::Data<ThreadSafeHourglassState>, event: HourglassEvent) {
     let mut data_unlocked_rw = data.write().unwrap();
-    let current_time_ms = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-    data_unlocked_rw.target_time_ms = current_time_ms + data_unlocked_rw.duration_ms;
-    data_unlocked_rw.ticking = true;
+    data_unlocked_rw.start(now_ms());
+    data_unlocked_rw.events.push(event);
 }
 
 fn stop_hourglass_timer(data: &web::Data<ThreadSafeHourglassState>) {
     let mut data_unlocked_rw = data.write().unwrap();
-    data_unlocked_rw.target_time_ms = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-    data_unlocked_rw.duration_ms = 0;
-    data_unlocked_rw.ticking = false;
+    data_unlocked_rw.stop(now_ms());
+    data_unlocked_rw.events.push(HourglassEvent::Stopped);
 }
 
 async fn index(_data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
@@ -63,7 +245,8 @@ async fn index(_data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
 }
 
 async fn start(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
-    start_hourglass_timer(&data);
+    data.write().unwrap().program = None;
+    start_hourglass_timer(&data, HourglassEvent::Started);
     "Started.".to_string()
 }
 
@@ -72,28 +255,42 @@ async fn stop(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
     "Stopped.".to_string()
 }
 
+async fn pause(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    data.write().unwrap().pause(now_ms());
+    "Paused.".to_string()
+}
+
+async fn resume(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    data.write().unwrap().resume(now_ms());
+    "Resumed.".to_string()
+}
+
+async fn acknowledge_alarm(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    match data.write().unwrap().acknowledge_alarm(now_ms()) {
+        true => "Alarm acknowledged.".to_string(),
+        false => "No alarm is active.".to_string(),
+    }
+}
+
+async fn snooze(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    match data.write().unwrap().snooze(now_ms()) {
+        true => "Alarm snoozed.".to_string(),
+        false => "No alarm is active.".to_string(),
+    }
+}
+
 async fn plus_minute(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
-    {
-        let mut data_unlocked_rw = data.write().unwrap();
-        data_unlocked_rw.duration_ms = data_unlocked_rw
-            .duration_ms
-            .checked_add(60000)
-            .unwrap_or(MAXIMUM_DURATION_MS)
-            .clamp(0, MAXIMUM_DURATION_MS);
+    match data.write().unwrap().add_minute(now_ms()) {
+        true => "Minute added.".to_string(),
+        false => "Error: The stopwatch has no minutes to add.".to_string(),
     }
-    start_hourglass_timer(&data);
-    "Minute added.".to_string()
 }
 
 async fn minus_minute(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
-    let mut data_unlocked_rw = data.write().unwrap();
-    let decremented_duration_ms = data_unlocked_rw.duration_ms.saturating_sub(60000);
-    if data_unlocked_rw.ticking {
-        let subtracted_ms = data_unlocked_rw.duration_ms - decremented_duration_ms;
-        data_unlocked_rw.target_time_ms -= subtracted_ms;
+    match data.write().unwrap().subtract_minute() {
+        true => "Minute subtracted.".to_string(),
+        false => "Error: The stopwatch has no minutes to subtract.".to_string(),
     }
-    data_unlocked_rw.duration_ms = decremented_duration_ms;
-    "Minute subtracted.".to_string()
 }
 
 async fn get_ticking(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
@@ -108,13 +305,293 @@ async fn get_duration_ms(data: web::Data<ThreadSafeHourglassState>) -> impl Resp
     format!("{}", data.read().unwrap().duration_ms)
 }
 
+async fn get_paused(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    format!("{:?}", data.read().unwrap().is_paused())
+}
+
+async fn get_last_alarm(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    match data.read().unwrap().last_alarm {
+        Some(alarm) => format!(
+            "expired_at_ms={} acknowledged_at_ms={} snoozed={}",
+            alarm.expired_at_ms,
+            alarm
+                .acknowledged_at_ms
+                .map_or("none".to_string(), |acknowledged_at_ms| acknowledged_at_ms
+                    .to_string()),
+            alarm.snoozed
+        ),
+        None => "none".to_string(),
+    }
+}
+
+async fn get_alarm_profiles(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    data.read()
+        .unwrap()
+        .alarm_profiles
+        .iter()
+        .map(|profile| profile.name.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+async fn get_alarm_profile(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    data.read().unwrap().alarm_profile.name.clone()
+}
+
+async fn set_alarm_profile(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    match parse_path_value::<String>(&req, "name") {
+        Ok(name) => {
+            let mut data_unlocked_rw = data.write().unwrap();
+            match data_unlocked_rw.select_alarm_profile(&name) {
+                true => {
+                    save_config(&data_unlocked_rw);
+                    format!("Setting alarm profile to {}.", name)
+                }
+                false => format!("Error: Unknown alarm profile {}.", name),
+            }
+        }
+        Err(error) => error,
+    }
+}
+
+/// Changes a user defined alarm profile or adds it and saves the config.
+fn update_alarm_profile<T, F>(
+    req: &HttpRequest,
+    data: &ThreadSafeHourglassState,
+    value: Result<T, String>,
+    update: F,
+) -> String
+where
+    F: FnOnce(&mut AlarmProfile, T),
+{
+    let name = match parse_path_value::<String>(req, "name") {
+        Ok(name) => name,
+        Err(error) => return error,
+    };
+    let value = match value {
+        Ok(value) => value,
+        Err(error) => return error,
+    };
+    let mut data_unlocked_rw = data.write().unwrap();
+    match data_unlocked_rw.update_alarm_profile(&name, |profile| update(profile, value)) {
+        true => {
+            save_config(&data_unlocked_rw);
+            format!("Updated alarm profile {}.", name)
+        }
+        false => format!("Error: The built-in alarm profile {} can't be changed.", name),
+    }
+}
+
+fn parse_volume_percent(req: &HttpRequest) -> Result<f32, String> {
+    parse_path_value::<u32>(req, "volume_percent")
+        .map(|volume_percent| volume_percent.clamp(0, 100) as f32 / 100f32)
+}
+
+async fn set_alarm_profile_visual(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let pattern = parse_path_value::<String>(&req, "pattern").and_then(|pattern| {
+        AlarmPattern::from_name(&pattern)
+            .ok_or(format!("Error: Unknown alarm pattern {}.", pattern))
+    });
+    let interval_ms = parse_path_value::<u128>(&req, "interval_ms");
+    let inverted = parse_path_value::<bool>(&req, "inverted");
+    let visual = match (pattern, interval_ms, inverted) {
+        (Ok(pattern), Ok(interval_ms), Ok(inverted)) => Ok((pattern, interval_ms, inverted)),
+        (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => Err(error),
+    };
+    update_alarm_profile(&req, &data, visual, |profile, (pattern, interval_ms, inverted)| {
+        profile.visual.pattern = pattern;
+        profile.visual.interval_ms = interval_ms.max(1);
+        profile.visual.inverted = inverted;
+    })
+}
+
+/// "none" as message shows an empty screen.
+async fn set_alarm_profile_message(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let message = parse_path_value::<String>(&req, "message");
+    update_alarm_profile(&req, &data, message, |profile, message| {
+        profile.visual.message = (message != "none").then_some(message);
+    })
+}
+
+async fn set_alarm_profile_sound(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let sound = parse_path_value::<String>(&req, "sound").and_then(|sound| {
+        SoundId::from_name(&sound).ok_or(format!("Error: Unknown sound {}.", sound))
+    });
+    let gap_ms = parse_path_value::<u64>(&req, "gap_ms");
+    let volume = parse_volume_percent(&req);
+    let sound = match (sound, gap_ms, volume) {
+        (Ok(sound), Ok(gap_ms), Ok(volume)) => Ok(AlarmSound {
+            sound,
+            gap_ms,
+            volume,
+        }),
+        (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => Err(error),
+    };
+    update_alarm_profile(&req, &data, sound, |profile, sound| {
+        profile.sound = Some(sound);
+    })
+}
+
+async fn delete_alarm_profile_sound(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    update_alarm_profile(&req, &data, Ok(()), |profile, ()| profile.sound = None)
+}
+
+async fn set_alarm_profile_escalation(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let after_ms = parse_path_value::<u128>(&req, "after_ms");
+    let interval_ms = parse_path_value::<u128>(&req, "interval_ms");
+    let sound_gap_ms = parse_path_value::<u64>(&req, "sound_gap_ms");
+    let volume = parse_volume_percent(&req);
+    let escalation = match (after_ms, interval_ms, sound_gap_ms, volume) {
+        (Ok(after_ms), Ok(interval_ms), Ok(sound_gap_ms), Ok(volume)) => Ok(Escalation {
+            after_ms,
+            interval_ms: interval_ms.max(1),
+            sound_gap_ms,
+            volume,
+        }),
+        (Err(error), _, _, _)
+        | (_, Err(error), _, _)
+        | (_, _, Err(error), _)
+        | (_, _, _, Err(error)) => Err(error),
+    };
+    update_alarm_profile(&req, &data, escalation, |profile, escalation| {
+        profile.escalation = Some(escalation);
+    })
+}
+
+async fn delete_alarm_profile_escalation(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    update_alarm_profile(&req, &data, Ok(()), |profile, ()| profile.escalation = None)
+}
+
+async fn set_alarm_profile_duration(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let duration_ms = parse_path_value::<u128>(&req, "duration_ms");
+    update_alarm_profile(&req, &data, duration_ms, |profile, duration_ms| {
+        profile.duration_ms = duration_ms;
+    })
+}
+
+async fn delete_alarm_profile(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    match parse_path_value::<String>(&req, "name") {
+        Ok(name) => {
+            let mut data_unlocked_rw = data.write().unwrap();
+            if !data_unlocked_rw.delete_alarm_profile(&name) {
+                return format!("Error: Unknown or built-in alarm profile {}.", name);
+            }
+            save_config(&data_unlocked_rw);
+            format!("Deleted alarm profile {}.", name)
+        }
+        Err(error) => error,
+    }
+}
+
+async fn get_tick_sound(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    let tick_settings = data.read().unwrap().tick_settings;
+    format!(
+        "enabled={} interval_s={} volume_percent={}",
+        tick_settings.enabled,
+        tick_settings.interval_s,
+        (tick_settings.volume * 100f32).round()
+    )
+}
+
+fn parse_path_value<T: FromStr>(req: &HttpRequest, name: &str) -> Result<T, String>
+where
+    T::Err: Debug,
+{
+    match req.match_info().get(name) {
+        Some(value) => value
+            .parse::<T>()
+            .map_err(|error| format!("Error: Unable to parse {}. {:?}", name, error)),
+        None => Err(format!("Error: No {} was given.", name)),
+    }
+}
+
+async fn set_tick_sound(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let enabled = parse_path_value::<bool>(&req, "enabled");
+    update_settings(&data, enabled, |state, enabled| {
+        state.tick_settings.enabled = enabled;
+        format!("Setting tick sound to {}.", enabled)
+    })
+}
+
+async fn set_tick_interval_s(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let interval_s = parse_path_value::<u32>(&req, "interval_s");
+    update_settings(&data, interval_s, |state, interval_s| {
+        let interval_s = interval_s.clamp(1, MAXIMUM_TICK_INTERVAL_S);
+        state.tick_settings.interval_s = interval_s;
+        format!("Setting tick interval to {}s.", interval_s)
+    })
+}
+
+async fn set_tick_volume_percent(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let volume_percent = parse_path_value::<u32>(&req, "volume_percent");
+    update_settings(&data, volume_percent, |state, volume_percent| {
+        let volume_percent = volume_percent.clamp(0, 100);
+        state.tick_settings.volume = volume_percent as f32 / 100f32;
+        format!("Setting tick volume to {}%.", volume_percent)
+    })
+}
+
+async fn get_warnings(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    format_warnings(&data.read().unwrap().warnings)
+}
+
+async fn set_warnings(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let warnings = parse_path_value::<String>(&req, "warnings").and_then(|warnings| {
+        parse_warnings(&warnings).map_err(|error| format!("Error: {}", error))
+    });
+    update_settings(&data, warnings, |state, warnings| {
+        let response = format!("Setting warnings to {}.", format_warnings(&warnings));
+        state.warnings = warnings;
+        response
+    })
+}
+
 async fn set_duration_ms(
     req: HttpRequest,
     data: web::Data<ThreadSafeHourglassState>,
 ) -> impl Responder {
     let duration_ms = req.match_info().get("duration_ms");
-    if duration_ms.is_some() {
-        match duration_ms.unwrap().parse::<u128>() {
+    if let Some(duration_ms) = duration_ms {
+        match duration_ms.parse::<u128>() {
             Ok(duration_ms) => {
                 let mut data_unlocked_rw = data.write().unwrap();
                 data_unlocked_rw.duration_ms = duration_ms.clamp(0, MAXIMUM_DURATION_MS);
@@ -123,12 +600,619 @@ async fn set_duration_ms(
                 return format!("Error: Unable to parse duration in ms. {:?}", error);
             }
         };
-        format!("Setting duration to {}ms.", duration_ms.unwrap())
+        format!("Setting duration to {}ms.", duration_ms)
     } else {
         "Error: No duration in ms was given.".to_string()
     }
 }
 
+async fn get_clock_face(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    data.read().unwrap().clock_face.name().to_string()
+}
+
+async fn set_clock_face(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let face = parse_path_value::<String>(&req, "face").and_then(|face| {
+        ClockFace::from_name(&face).ok_or(format!("Error: Unknown clock face {}.", face))
+    });
+    update_settings(&data, face, |state, face| {
+        state.clock_face = face;
+        format!("Setting clock face to {}.", face.name())
+    })
+}
+
+async fn get_presets(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(&data.read().unwrap().presets)
+}
+
+async fn set_preset(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let name = parse_path_value::<String>(&req, "name");
+    let duration_ms = parse_path_value::<u128>(&req, "duration_ms");
+    match (name, duration_ms) {
+        (Ok(name), Ok(duration_ms)) => {
+            let duration_ms = duration_ms.clamp(0, MAXIMUM_DURATION_MS);
+            let mut data_unlocked_rw = data.write().unwrap();
+            match data_unlocked_rw
+                .presets
+                .iter_mut()
+                .find(|preset| preset.name == name)
+            {
+                Some(preset) => preset.duration_ms = duration_ms,
+                None => data_unlocked_rw
+                    .presets
+                    .push(Preset::new(&name, duration_ms)),
+            }
+            save_config(&data_unlocked_rw);
+            format!("Setting preset {} to {}ms.", name, duration_ms)
+        }
+        (Err(error), _) | (_, Err(error)) => error,
+    }
+}
+
+/// Changes a preset, "none" as value removes the setting from the preset.
+fn update_preset<F>(
+    req: &HttpRequest,
+    data: &ThreadSafeHourglassState,
+    value: &str,
+    update: F,
+) -> String
+where
+    F: FnOnce(&mut Preset, Option<&str>) -> Result<(), String>,
+{
+    let name = match parse_path_value::<String>(req, "name") {
+        Ok(name) => name,
+        Err(error) => return error,
+    };
+    let value = match parse_path_value::<String>(req, value) {
+        Ok(value) => value,
+        Err(error) => return error,
+    };
+    let mut data_unlocked_rw = data.write().unwrap();
+    let preset = match data_unlocked_rw
+        .presets
+        .iter_mut()
+        .find(|preset| preset.name == name)
+    {
+        Some(preset) => preset,
+        None => return format!("Error: Unknown preset {}.", name),
+    };
+    let value = (value != "none").then_some(value.as_str());
+    match update(preset, value) {
+        Ok(()) => {
+            save_config(&data_unlocked_rw);
+            format!("Updated preset {}.", name)
+        }
+        Err(error) => error,
+    }
+}
+
+async fn set_preset_clock_face(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    update_preset(&req, &data, "face", |preset, face| {
+        preset.clock_face = match face {
+            Some(face) => Some(
+                ClockFace::from_name(face).ok_or(format!("Error: Unknown clock face {}.", face))?,
+            ),
+            None => None,
+        };
+        Ok(())
+    })
+}
+
+async fn set_preset_alarm_profile(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let profile_names: Vec<String> = data
+        .read()
+        .unwrap()
+        .alarm_profiles
+        .iter()
+        .map(|profile| profile.name.clone())
+        .collect();
+    update_preset(&req, &data, "alarm_profile", |preset, alarm_profile| {
+        if let Some(alarm_profile) = alarm_profile {
+            if !profile_names.iter().any(|name| name == alarm_profile) {
+                return Err(format!("Error: Unknown alarm profile {}.", alarm_profile));
+            }
+        }
+        preset.alarm_profile = alarm_profile.map(|alarm_profile| alarm_profile.to_string());
+        Ok(())
+    })
+}
+
+async fn delete_preset(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    match parse_path_value::<String>(&req, "name") {
+        Ok(name) => {
+            let mut data_unlocked_rw = data.write().unwrap();
+            let preset_count = data_unlocked_rw.presets.len();
+            data_unlocked_rw.presets.retain(|preset| preset.name != name);
+            if data_unlocked_rw.presets.len() == preset_count {
+                return format!("Error: Unknown preset {}.", name);
+            }
+            save_config(&data_unlocked_rw);
+            format!("Deleted preset {}.", name)
+        }
+        Err(error) => error,
+    }
+}
+
+async fn start_preset(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    match parse_path_value::<String>(&req, "name") {
+        Ok(name) => match data.write().unwrap().start_preset(&name, now_ms()) {
+            true => format!("Started preset {}.", name),
+            false => format!("Error: Unknown preset {}.", name),
+        },
+        Err(error) => error,
+    }
+}
+
+async fn get_programs(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(&data.read().unwrap().programs)
+}
+
+async fn start_program(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    match parse_path_value::<String>(&req, "name") {
+        Ok(name) => match data.write().unwrap().start_program(&name, now_ms()) {
+            true => format!("Started program {}.", name),
+            false => format!("Error: Unknown or empty program {}.", name),
+        },
+        Err(error) => error,
+    }
+}
+
+async fn get_program_progress(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    let data_unlocked_r = data.read().unwrap();
+    let progress = data_unlocked_r.program.as_ref().and_then(|run| run.progress());
+    HttpResponse::Ok().json(progress)
+}
+
+async fn start_stopwatch(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    data.write().unwrap().start_stopwatch(now_ms());
+    "Stopwatch started.".to_string()
+}
+
+async fn lap(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    match data.write().unwrap().lap(now_ms()) {
+        Some(elapsed_ms) => format!("Lap at {}ms.", elapsed_ms),
+        None => "Error: The stopwatch is not running.".to_string(),
+    }
+}
+
+async fn reset_stopwatch(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    let mut data_unlocked_rw = data.write().unwrap();
+    if data_unlocked_rw.mode == TimerMode::Stopwatch {
+        data_unlocked_rw.stop(now_ms());
+        data_unlocked_rw.events.push(HourglassEvent::Stopped);
+    }
+    data_unlocked_rw.laps.clear();
+    "Stopwatch reset.".to_string()
+}
+
+async fn get_mode(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    format!("{:?}", data.read().unwrap().mode).to_lowercase()
+}
+
+async fn get_elapsed_ms(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
+    format!("{}", data.read().unwrap().elapsed_ms(now_ms()))
+}
+
+/// The laps as JSON, each with the elapsed time and the time since the last lap.
+async fn get_laps(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    let laps = &data.read().unwrap().laps;
+    let mut last_elapsed_ms = 0;
+    let laps_json: Vec<serde_json::Value> = laps
+        .iter()
+        .map(|elapsed_ms| {
+            let lap_ms = elapsed_ms - last_elapsed_ms;
+            last_elapsed_ms = *elapsed_ms;
+            serde_json::json!({ "elapsed_ms": elapsed_ms, "lap_ms": lap_ms })
+        })
+        .collect();
+    HttpResponse::Ok().json(laps_json)
+}
+
+async fn get_schedules(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(&data.read().unwrap().schedules)
+}
+
+/// Adds or replaces a rule, it fires from its next occurrence on.
+async fn set_schedule(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let name = parse_path_value::<String>(&req, "name");
+    let weekdays = parse_path_value::<String>(&req, "weekdays").and_then(|weekdays| {
+        parse_weekdays(&weekdays).map_err(|error| format!("Error: {}", error))
+    });
+    let time = parse_path_value::<String>(&req, "time")
+        .and_then(|time| parse_time(&time).map_err(|error| format!("Error: {}", error)));
+    let preset = parse_path_value::<String>(&req, "preset");
+    match (name, weekdays, time, preset) {
+        (Ok(name), Ok(weekdays), Ok(time), Ok(preset)) => {
+            let mut data_unlocked_rw = data.write().unwrap();
+            if !data_unlocked_rw.presets.iter().any(|known| known.name == preset) {
+                return format!("Error: Unknown preset {}.", preset);
+            }
+            let rule = ScheduleRule::new(
+                &name,
+                weekdays,
+                time,
+                &preset,
+                chrono::Local::now().naive_local(),
+            );
+            data_unlocked_rw.schedules.retain(|known| known.name != name);
+            data_unlocked_rw.schedules.push(rule);
+            save_config(&data_unlocked_rw);
+            format!("Scheduled preset {} at {}.", preset, time.format("%H:%M"))
+        }
+        (Err(error), _, _, _)
+        | (_, Err(error), _, _)
+        | (_, _, Err(error), _)
+        | (_, _, _, Err(error)) => error,
+    }
+}
+
+async fn set_schedule_enabled(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let name = parse_path_value::<String>(&req, "name");
+    let enabled = parse_path_value::<bool>(&req, "enabled");
+    match (name, enabled) {
+        (Ok(name), Ok(enabled)) => {
+            let mut data_unlocked_rw = data.write().unwrap();
+            match data_unlocked_rw
+                .schedules
+                .iter_mut()
+                .find(|rule| rule.name == name)
+            {
+                Some(rule) => rule.enabled = enabled,
+                None => return format!("Error: Unknown schedule {}.", name),
+            }
+            save_config(&data_unlocked_rw);
+            format!("Setting schedule {} enabled to {}.", name, enabled)
+        }
+        (Err(error), _) | (_, Err(error)) => error,
+    }
+}
+
+async fn delete_schedule(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    match parse_path_value::<String>(&req, "name") {
+        Ok(name) => {
+            let mut data_unlocked_rw = data.write().unwrap();
+            let schedule_count = data_unlocked_rw.schedules.len();
+            data_unlocked_rw.schedules.retain(|rule| rule.name != name);
+            if data_unlocked_rw.schedules.len() == schedule_count {
+                return format!("Error: Unknown schedule {}.", name);
+            }
+            save_config(&data_unlocked_rw);
+            format!("Deleted schedule {}.", name)
+        }
+        Err(error) => error,
+    }
+}
+
+async fn get_quiet_hours(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(data.read().unwrap().quiet_hours)
+}
+
+/// Applies a parsed value to the state and saves the config.
+fn update_settings<T, F>(
+    data: &ThreadSafeHourglassState,
+    value: Result<T, String>,
+    update: F,
+) -> String
+where
+    F: FnOnce(&mut HourglassState, T) -> String,
+{
+    match value {
+        Ok(value) => {
+            let mut data_unlocked_rw = data.write().unwrap();
+            let response = update(&mut data_unlocked_rw, value);
+            save_config(&data_unlocked_rw);
+            response
+        }
+        Err(error) => error,
+    }
+}
+
+fn update_quiet_hours<T, F>(
+    data: &ThreadSafeHourglassState,
+    value: Result<T, String>,
+    update: F,
+) -> String
+where
+    F: FnOnce(&mut QuietHours, T) -> String,
+{
+    update_settings(data, value, |state, value| update(&mut state.quiet_hours, value))
+}
+
+/// Sets the period and enables the quiet hours.
+async fn set_quiet_hours(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let parse_time_value = |name: &str| {
+        parse_path_value::<String>(&req, name)
+            .and_then(|time| parse_time(&time).map_err(|error| format!("Error: {}", error)))
+    };
+    let period = parse_time_value("start").and_then(|start| Ok((start, parse_time_value("end")?)));
+    update_quiet_hours(&data, period, |quiet_hours, (start, end)| {
+        quiet_hours.start = start;
+        quiet_hours.end = end;
+        quiet_hours.enabled = true;
+        format!(
+            "Setting quiet hours from {} to {}.",
+            start.format("%H:%M"),
+            end.format("%H:%M")
+        )
+    })
+}
+
+async fn set_quiet_hours_enabled(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let enabled = parse_path_value::<bool>(&req, "enabled");
+    update_quiet_hours(&data, enabled, |quiet_hours, enabled| {
+        quiet_hours.enabled = enabled;
+        format!("Setting quiet hours enabled to {}.", enabled)
+    })
+}
+
+async fn set_quiet_hours_display(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let display = parse_path_value::<String>(&req, "display").and_then(|display| {
+        NightDisplay::from_name(&display)
+            .ok_or(format!("Error: Unknown night display {}.", display))
+    });
+    update_quiet_hours(&data, display, |quiet_hours, display| {
+        quiet_hours.display = display;
+        format!("Setting quiet hours display to {}.", display.name())
+    })
+}
+
+async fn set_quiet_hours_volume_percent(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let volume_percent = parse_path_value::<u32>(&req, "volume_percent");
+    update_quiet_hours(&data, volume_percent, |quiet_hours, volume_percent| {
+        let volume_percent = volume_percent.clamp(0, 100);
+        quiet_hours.volume = volume_percent as f32 / 100f32;
+        format!("Setting quiet hours volume to {}%.", volume_percent)
+    })
+}
+
+async fn set_quiet_hours_contrast(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let contrast = parse_path_value::<u8>(&req, "contrast");
+    update_quiet_hours(&data, contrast, |quiet_hours, contrast| {
+        quiet_hours.contrast = contrast;
+        format!("Setting quiet hours contrast to {}.", contrast)
+    })
+}
+
+async fn get_display_settings(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(data.read().unwrap().display)
+}
+
+fn parse_brightness(req: &HttpRequest) -> Result<Brightness, String> {
+    parse_path_value::<String>(req, "brightness").and_then(|brightness| {
+        Brightness::from_name(&brightness)
+            .ok_or(format!("Error: Unknown brightness {}.", brightness))
+    })
+}
+
+async fn set_display_brightness(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    update_settings(&data, parse_brightness(&req), |state, brightness| {
+        state.display.brightness = brightness;
+        format!("Setting display brightness to {}.", brightness.name())
+    })
+}
+
+async fn set_display_contrast(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let contrast = parse_path_value::<u8>(&req, "contrast");
+    update_settings(&data, contrast, |state, contrast| {
+        state.display.contrast = contrast;
+        format!("Setting display contrast to {}.", contrast)
+    })
+}
+
+async fn set_display_inverted(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let inverted = parse_path_value::<bool>(&req, "inverted");
+    update_settings(&data, inverted, |state, inverted| {
+        state.display.inverted = inverted;
+        format!("Setting display inverted to {}.", inverted)
+    })
+}
+
+async fn get_orientation(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(data.read().unwrap().orientation)
+}
+
+/// Rotation in degrees clockwise, mirrored flips left and right.
+async fn set_orientation(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let orientation = parse_path_value::<u16>(&req, "rotation")
+        .and_then(|degrees| {
+            Rotation::try_from(degrees).map_err(|error| format!("Error: {}", error))
+        })
+        .and_then(|rotation| {
+            Ok(Orientation {
+                rotation,
+                mirrored: parse_path_value::<bool>(&req, "mirrored")?,
+            })
+        });
+    update_settings(&data, orientation, |state, orientation| {
+        state.orientation = orientation;
+        format!(
+            "Setting orientation to {} degrees, mirrored {}.",
+            u16::from(orientation.rotation),
+            orientation.mirrored
+        )
+    })
+}
+
+async fn get_render_settings(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(data.read().unwrap().render)
+}
+
+async fn set_max_fps(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let max_fps = parse_path_value::<u32>(&req, "max_fps").and_then(|max_fps| {
+        match (1..=MAX_FPS_LIMIT).contains(&max_fps) {
+            true => Ok(max_fps),
+            false => Err(format!("Error: The max fps must be 1 to {}.", MAX_FPS_LIMIT)),
+        }
+    });
+    update_settings(&data, max_fps, |state, max_fps| {
+        state.render.max_fps = max_fps;
+        format!("Setting max fps to {}.", max_fps)
+    })
+}
+
+async fn set_transitions(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let transitions = parse_path_value::<bool>(&req, "transitions");
+    update_settings(&data, transitions, |state, transitions| {
+        state.render.transitions = transitions;
+        format!("Setting transitions to {}.", transitions)
+    })
+}
+
+async fn get_ambient_schedule(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(&data.read().unwrap().ambient)
+}
+
+async fn set_ambient_schedule_enabled(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let enabled = parse_path_value::<bool>(&req, "enabled");
+    update_settings(&data, enabled, |state, enabled| {
+        state.ambient.enabled = enabled;
+        format!("Setting ambient schedule enabled to {}.", enabled)
+    })
+}
+
+fn parse_start(req: &HttpRequest) -> Result<NaiveTime, String> {
+    parse_path_value::<String>(req, "start")
+        .and_then(|start| parse_time(&start).map_err(|error| format!("Error: {}", error)))
+}
+
+async fn set_ambient_step(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let step = parse_start(&req).and_then(|start| {
+        Ok(AmbientStep {
+            start,
+            brightness: parse_brightness(&req)?,
+            contrast: parse_path_value::<u8>(&req, "contrast")?,
+        })
+    });
+    update_settings(&data, step, |state, step| {
+        state.ambient.set_step(step);
+        format!("Setting ambient step at {}.", step.start.format("%H:%M"))
+    })
+}
+
+async fn delete_ambient_step(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    // An unknown step leaves the config alone.
+    let start = parse_start(&req).and_then(|start| {
+        let state = data.read().unwrap();
+        match state.ambient.steps.iter().any(|step| step.start == start) {
+            true => Ok(start),
+            false => Err(format!("Error: No ambient step at {}.", start.format("%H:%M"))),
+        }
+    });
+    update_settings(&data, start, |state, start| {
+        state.ambient.steps.retain(|step| step.start != start);
+        format!("Deleted ambient step at {}.", start.format("%H:%M"))
+    })
+}
+
+async fn get_idle_settings(data: web::Data<ThreadSafeHourglassState>) -> HttpResponse {
+    HttpResponse::Ok().json(data.read().unwrap().idle)
+}
+
+async fn set_idle_timeouts(
+    req: HttpRequest,
+    data: web::Data<ThreadSafeHourglassState>,
+) -> impl Responder {
+    let timeouts = parse_path_value::<u128>(&req, "shift_after_s").and_then(|shift_after_s| {
+        let screensaver_after_s = parse_path_value::<u128>(&req, "screensaver_after_s")?;
+        let off_after_s = parse_path_value::<u128>(&req, "off_after_s")?;
+        Ok((shift_after_s, screensaver_after_s, off_after_s))
+    });
+    // Shrinking timeouts are rejected before anything is saved.
+    let timeouts = timeouts.and_then(|(shift_after_s, screensaver_after_s, off_after_s)| {
+        match shift_after_s <= screensaver_after_s && screensaver_after_s <= off_after_s {
+            true => Ok((shift_after_s, screensaver_after_s, off_after_s)),
+            false => Err(
+                "Error: The timeouts must not shrink from shift to screensaver to off.".to_string(),
+            ),
+        }
+    });
+    update_settings(
+        &data,
+        timeouts,
+        |state, (shift_after_s, screensaver_after_s, off_after_s)| {
+            // Absurdly long timeouts just never end.
+            state.idle.shift_after_ms = shift_after_s.saturating_mul(1000);
+            state.idle.screensaver_after_ms = screensaver_after_s.saturating_mul(1000);
+            state.idle.off_after_ms = off_after_s.saturating_mul(1000);
+            format!(
+                "Setting idle timeouts to {}s, {}s and {}s.",
+                shift_after_s, screensaver_after_s, off_after_s
+            )
+        },
+    )
+}
+
 async fn end_service(data: web::Data<ThreadSafeHourglassState>) -> impl Responder {
     data.write().unwrap().finalize = true;
     "Webservice teared down.".to_string()
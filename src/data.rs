@@ -1,7 +1,7 @@
 use crate::gui::display_control::Pixmap;
 
 #[rustfmt::skip]
-pub const WELCOME_SCREEN_PIXMAP: Pixmap = Pixmap {
+pub const WELCOME_SCREEN_PIXMAP: Pixmap<32, 128> = Pixmap {
     data:[
     [0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
     [0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],
@@ -1,33 +1,87 @@
-use crate::gui::display_control::{Color, DisplayBuffer, HEIGHT, WIDTH};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Drawable, Point as GraphicsPoint, Primitive};
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
 
+use crate::gui::display_control::DisplayBuffer;
+
+const BLOCK_SIZE: isize = 7;
+const BLOCK_GAP: isize = 1;
+
+/// One block per minute, as many in a row as fit across the screen, rows
+/// from the bottom up. On a landscape screen the rows become columns from
+/// the left.
 pub fn draw_block_clock(remaining_seconds: u128, frame_buffer: &mut DisplayBuffer) {
+    let is_landscape = frame_buffer.width() > frame_buffer.height();
     let minutes = 1 + remaining_seconds as isize / 60;
     let seconds = remaining_seconds as isize % 60;
 
-    // TODO maybe not magic numbers
-    let rect_size = 6;
-    let rect_x_positions = vec![30, 22, 14, 6];
-    let x_offset = 1;
-    let rect_per_row: usize = 4;
+    let height = frame_buffer.height();
+    let row_length = isize::min(frame_buffer.width(), height);
+    // The layout counts rows from the bottom, embedded-graphics from the top.
+    let corner = |x: isize, y: isize| GraphicsPoint::new(x as i32, (height - 1 - y) as i32);
+    let pitch = BLOCK_SIZE + BLOCK_GAP;
+    let blocks_per_row = isize::max(1, row_length / pitch);
 
     for minute in 0..minutes {
-        let row = minute / rect_per_row as isize;
-        let minute_in_row = minute % rect_per_row as isize;
-        let x0 = rect_x_positions[minute as usize % rect_per_row] + x_offset;
-        let y0 = 1 + row * rect_size + row * 2;
-        let x1 = x0 - rect_size;
+        let row = minute / blocks_per_row;
+        let x0 = row_length - 1 - (minute % blocks_per_row) * pitch;
+        let y0 = BLOCK_GAP + row * pitch;
+        let x1 = x0 - (BLOCK_SIZE - 1);
 
         let y1 = if minute == minutes - 1 {
-            let segments_filled =
-                f32::floor((1 + rect_size) as f32 * seconds as f32 / 60f32) as isize;
+            let segments_filled = f32::floor(BLOCK_SIZE as f32 * seconds as f32 / 60f32) as isize;
             let blinking_subtractor = if seconds % 2 == 1 { 0 } else { 1 };
             y0 + segments_filled - blinking_subtractor
         } else {
-            y0 + rect_size
+            y0 + BLOCK_SIZE - 1
         };
 
         if y1 >= y0 {
-            frame_buffer.draw_box_with_coords(x0, y0, x1, y1, &Color::White, &Color::White);
+            let (x0, y0, x1, y1) = match is_landscape {
+                true => (y0, x0, y1, x1),
+                false => (x0, y0, x1, y1),
+            };
+            Rectangle::with_corners(corner(x0, y0), corner(x1, y1))
+                .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+                .draw(frame_buffer)
+                .unwrap();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::display_control::{Color, Orientation, Point, Rotation};
+
+    #[test]
+    fn test_landscape_rows_become_columns() {
+        let mut display = DisplayBuffer::new(32, 128);
+        display.set_orientation(Orientation {
+            rotation: Rotation::R270,
+            mirrored: false,
+        });
+
+        draw_block_clock(4 * 60 + 30, &mut display);
+
+        let is_lit = |x, y| display.get_pixel_color(&Point { x, y }) == Ok(Color::White);
+        // The first minute is at the top left, the fifth starts the next column.
+        assert!(is_lit(1, 31) && is_lit(7, 25));
+        assert!(!is_lit(4, 24));
+        assert!(is_lit(9, 31));
+        assert!((17..128).all(|x| (0..32).all(|y| !is_lit(x, y))));
+    }
+
+    #[test]
+    fn test_rows_fill_the_screen_width() {
+        let mut display = DisplayBuffer::new(16, 24);
+
+        draw_block_clock(2 * 60 + 59, &mut display);
+
+        let is_lit = |x, y| display.get_pixel_color(&Point { x, y }) == Ok(Color::White);
+        // Two blocks in the first row from the right, the third starts the next.
+        assert!(is_lit(15, 1) && is_lit(9, 7) && !is_lit(8, 1));
+        assert!(is_lit(7, 1) && is_lit(1, 7) && !is_lit(0, 1));
+        assert!(is_lit(15, 9) && is_lit(9, 15) && !is_lit(7, 9));
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use crate::gui::block_clock::draw_block_clock;
+use crate::gui::digit_time::draw_digit_time;
+use crate::gui::display_control::DisplayBuffer;
+use crate::gui::pie_clock::{draw_pie_clock, sector_step};
+
+/// How the remaining time is drawn while the timer runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockFace {
+    Blocks,
+    Digits,
+    Pie,
+}
+
+impl ClockFace {
+    pub const ALL: [ClockFace; 3] = [ClockFace::Blocks, ClockFace::Digits, ClockFace::Pie];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClockFace::Blocks => "blocks",
+            ClockFace::Digits => "digits",
+            ClockFace::Pie => "pie",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ClockFace> {
+        ClockFace::ALL
+            .iter()
+            .copied()
+            .find(|face| face.name() == name)
+    }
+
+    /// What the face shows of the clock seconds, it only has to be redrawn
+    /// when this changes.
+    pub fn frame(&self, clock_seconds: u128, span_seconds: u128) -> u128 {
+        match self {
+            ClockFace::Blocks | ClockFace::Digits => clock_seconds,
+            ClockFace::Pie => sector_step(clock_seconds, span_seconds),
+        }
+    }
+
+    /// Draws the clock seconds, a part of the span of seconds the clock runs.
+    pub fn draw(&self, clock_seconds: u128, span_seconds: u128, frame_buffer: &mut DisplayBuffer) {
+        match self {
+            ClockFace::Blocks => draw_block_clock(clock_seconds, frame_buffer),
+            ClockFace::Digits => draw_digit_time(clock_seconds, frame_buffer),
+            ClockFace::Pie => draw_pie_clock(clock_seconds, span_seconds, frame_buffer),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::gui::display_control::{ClipRect, Color, DisplayBuffer, Point};
+
+/// How the pixels of a source buffer combine with the ones below.
+#[derive(Clone, Copy)]
+pub enum BlitOp<'a> {
+    Copy,
+    Or,
+    And,
+    Xor,
+    /// Copies only the pixels lit in the mask.
+    Masked(&'a DisplayBuffer),
+}
+
+impl BlitOp<'_> {
+    fn combine(&self, below: u8, source: u8, mask: u8) -> u8 {
+        match self {
+            BlitOp::Copy => source,
+            BlitOp::Or => below | source,
+            BlitOp::And => below & source,
+            BlitOp::Xor => below ^ source,
+            BlitOp::Masked(_) => (below & !mask) | (source & mask),
+        }
+    }
+}
+
+/// Blits combine whole buffers of the same size and orientation, a clip of
+/// the target limits them to its rectangle.
+impl DisplayBuffer {
+    pub fn blit(&mut self, source: &DisplayBuffer, op: BlitOp) {
+        assert!(source.panel_width() == self.panel_width());
+        assert!(source.panel_height() == self.panel_height());
+        assert!(source.orientation() == self.orientation());
+        let mask = match op {
+            BlitOp::Masked(mask) => Some(mask),
+            _ => None,
+        };
+        match self.clip() {
+            None => {
+                for (index, byte) in self.buffer.iter_mut().enumerate() {
+                    let mask = mask.map_or(0xFF, |mask| mask.buffer[index]);
+                    *byte = op.combine(*byte, source.buffer[index], mask);
+                }
+            }
+            Some(clip) => {
+                fn is_lit(buffer: &DisplayBuffer, point: &Point) -> u8 {
+                    u8::from(buffer.get_pixel_color(point) == Ok(Color::White))
+                }
+                for y in clip.bottom.max(0)..=clip.top.min(self.height() - 1) {
+                    for x in clip.left.max(0)..=clip.right.min(self.width() - 1) {
+                        let point = Point { x, y };
+                        let mask = mask.map_or(1, |mask| is_lit(mask, &point));
+                        let below = is_lit(self, &point);
+                        let color = match op.combine(below, is_lit(source, &point), mask) & 1 {
+                            1 => Color::White,
+                            _ => Color::Black,
+                        };
+                        self.set_pixel_color(&point, &color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How a layer goes onto the layers below it, a masked layer owns its mask.
+pub enum LayerMode {
+    Copy,
+    Or,
+    And,
+    Xor,
+    Masked(DisplayBuffer),
+}
+
+pub struct Layer {
+    pub buffer: DisplayBuffer,
+    pub mode: LayerMode,
+    /// Limits the layer to a part of the screen.
+    pub clip: Option<ClipRect>,
+    pub visible: bool,
+}
+
+impl Layer {
+    /// Blanks the layer and hides it until it is drawn again.
+    pub fn clear(&mut self) {
+        self.buffer.fill_with_black();
+        if let LayerMode::Masked(mask) = &mut self.mode {
+            mask.fill_with_black();
+        }
+        self.clip = None;
+        self.visible = false;
+    }
+}
+
+/// Merges the clock face in the base buffer with the overlays on top, in
+/// the order they were added. Faces draw into the base without knowing
+/// about the overlays.
+pub struct Compositor {
+    pub base: DisplayBuffer,
+    layers: Vec<Layer>,
+}
+
+impl Compositor {
+    pub fn new(like: &DisplayBuffer) -> Self {
+        Compositor {
+            base: DisplayBuffer::new_like(like),
+            layers: Vec::new(),
+        }
+    }
+
+    /// A hidden overlay above the ones before, its index is returned. The
+    /// mask of a masked layer needs the size of the base.
+    pub fn add_layer(&mut self, mode: LayerMode) -> usize {
+        self.layers.push(Layer {
+            buffer: DisplayBuffer::new_like(&self.base),
+            mode,
+            clip: None,
+            visible: false,
+        });
+        self.layers.len() - 1
+    }
+
+    pub fn layer(&mut self, index: usize) -> &mut Layer {
+        &mut self.layers[index]
+    }
+
+    /// Follows a new size or orientation of the screen, all layers are
+    /// cleared and have to be drawn again.
+    pub fn match_buffer(&mut self, like: &DisplayBuffer) {
+        self.base = DisplayBuffer::new_like(like);
+        for layer in self.layers.iter_mut() {
+            layer.buffer = DisplayBuffer::new_like(like);
+            if let LayerMode::Masked(mask) = &mut layer.mode {
+                *mask = DisplayBuffer::new_like(like);
+            }
+            layer.clear();
+        }
+    }
+
+    /// Writes the base with the visible overlays on top into the target.
+    pub fn compose(&self, target: &mut DisplayBuffer) {
+        target.blit(&self.base, BlitOp::Copy);
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            let op = match &layer.mode {
+                LayerMode::Copy => BlitOp::Copy,
+                LayerMode::Or => BlitOp::Or,
+                LayerMode::And => BlitOp::And,
+                LayerMode::Xor => BlitOp::Xor,
+                LayerMode::Masked(mask) => BlitOp::Masked(mask),
+            };
+            if let Some(clip) = layer.clip {
+                target.push_clip(clip);
+            }
+            target.blit(&layer.buffer, op);
+            if layer.clip.is_some() {
+                target.pop_clip();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer_of(byte: u8) -> DisplayBuffer {
+        let mut buffer = DisplayBuffer::new(8, 2);
+        buffer.buffer.fill(byte);
+        buffer
+    }
+
+    #[test]
+    fn test_blit_operations() {
+        let source = buffer_of(0b1100);
+        let mask = buffer_of(0b0101);
+        for (op, expected) in [
+            (BlitOp::Copy, 0b1100),
+            (BlitOp::Or, 0b1110),
+            (BlitOp::And, 0b1000),
+            (BlitOp::Xor, 0b0110),
+            (BlitOp::Masked(&mask), 0b1110),
+        ] {
+            let mut target = buffer_of(0b1010);
+            target.blit(&source, op);
+            assert_eq!(target.buffer, vec![expected; 2]);
+
+            // The same pixel by pixel inside of a clip, the rest untouched.
+            let mut target = buffer_of(0b1010);
+            target.push_clip(ClipRect {
+                left: 0,
+                bottom: 1,
+                right: 7,
+                top: 1,
+            });
+            target.blit(&source, op);
+            assert_eq!(target.buffer, vec![0b1010, expected]);
+        }
+    }
+
+    #[test]
+    fn test_compose_overlays_on_the_base() {
+        let like = DisplayBuffer::new(8, 8);
+        let mut compositor = Compositor::new(&like);
+        let banner = compositor.add_layer(LayerMode::Copy);
+        let border = compositor.add_layer(LayerMode::Xor);
+        compositor.base.fill_with_white();
+
+        // The banner blanks the top two rows, the border flips the left column.
+        compositor.layer(banner).clip = Some(ClipRect {
+            left: 0,
+            bottom: 6,
+            right: 7,
+            top: 7,
+        });
+        compositor.layer(banner).visible = true;
+        let layer = compositor.layer(border);
+        for y in 0..8 {
+            layer
+                .buffer
+                .set_pixel_color(&Point { x: 0, y }, &Color::White);
+        }
+        layer.visible = true;
+        let mut target = DisplayBuffer::new_like(&like);
+        compositor.compose(&mut target);
+        assert_eq!(
+            target.buffer,
+            vec![0xFE, 0xFE, 0xFE, 0xFE, 0xFE, 0xFE, 0x01, 0x01]
+        );
+        assert_eq!(target.clip(), None);
+
+        // Hidden layers are left out.
+        compositor.layer(banner).clear();
+        compositor.layer(border).clear();
+        compositor.compose(&mut target);
+        assert_eq!(target.buffer, vec![0xFF; 8]);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::gui::display_control::{text_width, AsciiText, DisplayBuffer, Point, GLYPH_HEIGHT};
+
+const DIGIT_SCALE: isize = 3;
+const DIGIT_SPACING: isize = 3;
+const LINE_GAP: isize = 8;
+
+// The largest scale up to DIGIT_SCALE at which the lines fit the screen.
+fn fitting_scale(lines: &[&str], width: isize, height: isize) -> isize {
+    let line_count = lines.len() as isize;
+    (1..=DIGIT_SCALE)
+        .rev()
+        .find(|scale| {
+            let block_height = line_count * GLYPH_HEIGHT * scale + (line_count - 1) * LINE_GAP;
+            block_height <= height
+                && lines
+                    .iter()
+                    .all(|line| text_width(line, DIGIT_SPACING, *scale) <= width)
+        })
+        .unwrap_or(1)
+}
+
+/// Big minutes above big seconds, each as two digits. A landscape screen
+/// shows them in one line. Small screens get smaller digits.
+pub fn draw_digit_time(seconds: u128, frame_buffer: &mut DisplayBuffer) {
+    let minutes_text = format!("{:02}", seconds / 60);
+    let seconds_text = format!("{:02}", seconds % 60);
+    let (width, height) = (frame_buffer.width(), frame_buffer.height());
+    let scale = match width > height {
+        true => fitting_scale(&["00:00"], width, height),
+        false => fitting_scale(&["00", "00"], width, height),
+    };
+    let line_height = GLYPH_HEIGHT * scale;
+    let lines = if width > height {
+        vec![(format!("{}:{}", minutes_text, seconds_text), (height - line_height) / 2)]
+    } else {
+        vec![
+            (minutes_text, height / 2 + LINE_GAP / 2),
+            (seconds_text, height / 2 - LINE_GAP / 2 - line_height),
+        ]
+    };
+
+    for (text, y) in lines {
+        let x = (width - text_width(&text, DIGIT_SPACING, scale)) / 2;
+        let text = AsciiText::new(&text, Point { x, y }, DIGIT_SPACING, false);
+        frame_buffer.write_ascii_text(text.scaled(scale));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::display_control::{Color, Orientation, Rotation};
+
+    #[test]
+    fn test_digits_are_centered_on_the_screen() {
+        let mut display = DisplayBuffer::new(32, 128);
+
+        draw_digit_time(8 * 60 + 8, &mut display);
+
+        // "08" is 27 pixels wide, so the columns 0, 1, 30 and 31 stay dark.
+        for y in 0..display.height() {
+            for x in [0, 1, 30, 31] {
+                assert!(display.get_pixel_color(&Point { x, y }) == Ok(Color::Black));
+            }
+        }
+        // The middle bar of the upper 8, the glyph row 2 of the minutes.
+        let y = display.height() / 2 + LINE_GAP / 2 + 3 * DIGIT_SCALE;
+        assert!(display.get_pixel_color(&Point { x: 22, y }) == Ok(Color::White));
+    }
+
+    #[test]
+    fn test_landscape_shows_one_line() {
+        let mut display = DisplayBuffer::new(32, 128);
+        display.set_orientation(Orientation {
+            rotation: Rotation::R90,
+            mirrored: false,
+        });
+
+        draw_digit_time(8 * 60 + 8, &mut display);
+
+        // "08:08" is 72 pixels wide and 18 high, centered on 128x32.
+        let (left, bottom) = (28, 7);
+        let is_lit = |x, y| display.get_pixel_color(&Point { x, y }) == Ok(Color::White);
+        assert!((0..32).all(|y| !is_lit(left - 1, y) && !is_lit(left + 72, y)));
+        assert!((0..128).all(|x| !is_lit(x, bottom - 1) && !is_lit(x, bottom + 18)));
+        assert!(is_lit(left, bottom + 3 * DIGIT_SCALE));
+    }
+
+    #[test]
+    fn test_small_screen_gets_small_digits() {
+        // A strip of four LED matrix modules.
+        let mut display = DisplayBuffer::new(8, 32);
+        display.set_orientation(Orientation {
+            rotation: Rotation::R270,
+            mirrored: false,
+        });
+
+        draw_digit_time(8 * 60 + 8, &mut display);
+
+        // "08:08" at scale 1 is exactly 32 pixels wide and 6 high.
+        let is_lit = |x, y| display.get_pixel_color(&Point { x, y }) == Ok(Color::White);
+        assert!(is_lit(0, 2) && is_lit(31, 2));
+        assert!((0..32).all(|x| !is_lit(x, 0) && !is_lit(x, 7)));
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(target_arch = "arm")]
+use rppal::gpio::OutputPin;
+#[cfg(target_arch = "arm")]
+use rppal::i2c::I2c;
+#[cfg(target_arch = "arm")]
+use rppal::spi::Spi;
+
+/// I2C address of the SSD1306 and SH1106 modules, 0x3D if D/C is pulled high.
+pub const OLED_I2C_ADDRESS: u16 = 0x3C;
+
+#[cfg_attr(test, mockall::automock)]
+pub trait SpiInterface {
+    fn send_bytes(&mut self, bytes: &[u8]);
+}
+
+#[cfg_attr(test, mockall::automock)]
+pub trait PinInterface {
+    fn set_pin(&mut self, value: bool);
+}
+
+#[cfg_attr(test, mockall::automock)]
+pub trait I2cInterface {
+    /// One write to the device, from the start to the stop condition.
+    fn send_bytes(&mut self, bytes: &[u8]);
+}
+
+#[cfg(target_arch = "arm")]
+impl SpiInterface for Spi {
+    fn send_bytes(&mut self, bytes: &[u8]) {
+        self.write(bytes).unwrap();
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl PinInterface for OutputPin {
+    fn set_pin(&mut self, value: bool) {
+        match value {
+            true => self.set_high(),
+            false => self.set_low(),
+        };
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl I2cInterface for I2c {
+    fn send_bytes(&mut self, bytes: &[u8]) {
+        self.write(bytes).unwrap();
+    }
+}
+
+/// How commands and display data reach an OLED controller.
+pub trait OledBus {
+    fn send_commands(&mut self, commands: &[u8]);
+    fn send_data(&mut self, data: &[u8]);
+}
+
+/// 4-wire SPI, the D/C pin tells commands from data.
+pub struct SpiBus<T, U> {
+    pub spi: T,
+    pub dc_pin: U,
+}
+
+impl<T: SpiInterface, U: PinInterface> OledBus for SpiBus<T, U> {
+    fn send_commands(&mut self, commands: &[u8]) {
+        self.dc_pin.set_pin(false);
+        self.spi.send_bytes(commands);
+    }
+
+    fn send_data(&mut self, data: &[u8]) {
+        self.dc_pin.set_pin(true);
+        self.spi.send_bytes(data);
+    }
+}
+
+/// I2C, a control byte in front tells commands from data.
+pub struct I2cBus<T> {
+    pub i2c: T,
+}
+
+impl<T: I2cInterface> I2cBus<T> {
+    fn send_with_control_byte(&mut self, control: u8, bytes: &[u8]) {
+        let mut message = Vec::with_capacity(bytes.len() + 1);
+        message.push(control);
+        message.extend_from_slice(bytes);
+        self.i2c.send_bytes(&message);
+    }
+}
+
+impl<T: I2cInterface> OledBus for I2cBus<T> {
+    fn send_commands(&mut self, commands: &[u8]) {
+        self.send_with_control_byte(0x00, commands);
+    }
+
+    fn send_data(&mut self, data: &[u8]) {
+        self.send_with_control_byte(0x40, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockall::predicate::*;
+    use mockall::*;
+
+    #[test]
+    fn test_i2c_control_bytes() {
+        let mut bus = I2cBus {
+            i2c: MockI2cInterface::new(),
+        };
+
+        let mut sequence = Sequence::new();
+        for expected in [vec![0x00, 0xAE, 0xD5], vec![0x40, 0xFF]] {
+            bus.i2c
+                .expect_send_bytes()
+                .withf(move |bytes: &[u8]| bytes == expected)
+                .return_const(())
+                .times(1)
+                .in_sequence(&mut sequence);
+        }
+
+        bus.send_commands(&[0xAE, 0xD5]);
+        bus.send_data(&[0xFF]);
+    }
+}
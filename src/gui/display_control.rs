@@ -1,6 +1,62 @@
-pub const WIDTH: isize = 32;
-pub const HEIGHT: isize = 128;
-const DISPLAY_BUFFER_SIZE: usize = 512; // height * width / 8
+use std::convert::TryFrom;
+
+use embedded_graphics::image::Image;
+use embedded_graphics::prelude::{DrawTarget, Drawable, Pixel, Point as GraphicsPoint};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_CONTRAST: u8 = 0x80; // SSD1305 value after reset
+
+pub const GLYPH_WIDTH: isize = 4;
+pub const GLYPH_HEIGHT: isize = 6;
+const TEXT_LINE_SPACING: isize = 3;
+
+// Rows from top to bottom, the bits 3 to 0 are the columns from left to right.
+// Lowercase letters are shown as uppercase.
+const GLYPHS: [(char, [u8; 6]); 43] = [
+    ('A', [0b0110, 0b1001, 0b1001, 0b1111, 0b1001, 0b1001]),
+    ('B', [0b1110, 0b1001, 0b1110, 0b1001, 0b1001, 0b1110]),
+    ('C', [0b0111, 0b1000, 0b1000, 0b1000, 0b1000, 0b0111]),
+    ('D', [0b1110, 0b1001, 0b1001, 0b1001, 0b1001, 0b1110]),
+    ('E', [0b1111, 0b1000, 0b1110, 0b1000, 0b1000, 0b1111]),
+    ('F', [0b1111, 0b1000, 0b1110, 0b1000, 0b1000, 0b1000]),
+    ('G', [0b0111, 0b1000, 0b1000, 0b1011, 0b1001, 0b0111]),
+    ('H', [0b1001, 0b1001, 0b1111, 0b1001, 0b1001, 0b1001]),
+    ('I', [0b1110, 0b0100, 0b0100, 0b0100, 0b0100, 0b1110]),
+    ('J', [0b0011, 0b0001, 0b0001, 0b0001, 0b1001, 0b0110]),
+    ('K', [0b1001, 0b1010, 0b1100, 0b1100, 0b1010, 0b1001]),
+    ('L', [0b1000, 0b1000, 0b1000, 0b1000, 0b1000, 0b1111]),
+    ('M', [0b1001, 0b1111, 0b1111, 0b1001, 0b1001, 0b1001]),
+    ('N', [0b1001, 0b1101, 0b1101, 0b1011, 0b1011, 0b1001]),
+    ('O', [0b0110, 0b1001, 0b1001, 0b1001, 0b1001, 0b0110]),
+    ('P', [0b1110, 0b1001, 0b1001, 0b1110, 0b1000, 0b1000]),
+    ('Q', [0b0110, 0b1001, 0b1001, 0b1001, 0b1010, 0b0101]),
+    ('R', [0b1110, 0b1001, 0b1001, 0b1110, 0b1010, 0b1001]),
+    ('S', [0b0111, 0b1000, 0b0110, 0b0001, 0b0001, 0b1110]),
+    ('T', [0b1110, 0b0100, 0b0100, 0b0100, 0b0100, 0b0100]),
+    ('U', [0b1001, 0b1001, 0b1001, 0b1001, 0b1001, 0b0110]),
+    ('V', [0b1001, 0b1001, 0b1001, 0b1001, 0b1010, 0b0100]),
+    ('W', [0b1001, 0b1001, 0b1001, 0b1111, 0b1111, 0b1001]),
+    ('X', [0b1001, 0b1001, 0b0110, 0b0110, 0b1001, 0b1001]),
+    ('Y', [0b1010, 0b1010, 0b1010, 0b0100, 0b0100, 0b0100]),
+    ('Z', [0b1111, 0b0001, 0b0010, 0b0100, 0b1000, 0b1111]),
+    ('0', [0b0110, 0b1001, 0b1011, 0b1101, 0b1001, 0b0110]),
+    ('1', [0b0100, 0b1100, 0b0100, 0b0100, 0b0100, 0b1110]),
+    ('2', [0b0110, 0b1001, 0b0010, 0b0100, 0b1000, 0b1111]),
+    ('3', [0b1110, 0b0001, 0b0110, 0b0001, 0b0001, 0b1110]),
+    ('4', [0b0010, 0b0110, 0b1010, 0b1111, 0b0010, 0b0010]),
+    ('5', [0b1111, 0b1000, 0b1110, 0b0001, 0b0001, 0b1110]),
+    ('6', [0b0110, 0b1000, 0b1110, 0b1001, 0b1001, 0b0110]),
+    ('7', [0b1111, 0b0001, 0b0010, 0b0100, 0b0100, 0b0100]),
+    ('8', [0b0110, 0b1001, 0b0110, 0b1001, 0b1001, 0b0110]),
+    ('9', [0b0110, 0b1001, 0b1001, 0b0111, 0b0001, 0b0110]),
+    (':', [0b0000, 0b0100, 0b0000, 0b0000, 0b0100, 0b0000]),
+    ('\'', [0b0100, 0b0100, 0b0000, 0b0000, 0b0000, 0b0000]),
+    ('!', [0b0100, 0b0100, 0b0100, 0b0100, 0b0000, 0b0100]),
+    ('.', [0b0000, 0b0000, 0b0000, 0b0000, 0b0000, 0b0100]),
+    ('-', [0b0000, 0b0000, 0b1110, 0b0000, 0b0000, 0b0000]),
+    ('/', [0b0001, 0b0001, 0b0010, 0b0100, 0b1000, 0b1000]),
+    (' ', [0b0000, 0b0000, 0b0000, 0b0000, 0b0000, 0b0000]),
+];
 
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct Point {
@@ -8,26 +64,196 @@ pub struct Point {
     pub y: isize,
 }
 
-#[derive(Eq, PartialEq)]
+/// A rectangle of logical coordinates, the edges included. It is empty if
+/// left is right of right or bottom above top.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct ClipRect {
+    pub left: isize,
+    pub bottom: isize,
+    pub right: isize,
+    pub top: isize,
+}
+
+impl ClipRect {
+    pub fn contains(&self, point: &Point) -> bool {
+        (self.left..=self.right).contains(&point.x) && (self.bottom..=self.top).contains(&point.y)
+    }
+
+    pub fn intersection(&self, other: &ClipRect) -> ClipRect {
+        ClipRect {
+            left: self.left.max(other.left),
+            bottom: self.bottom.max(other.bottom),
+            right: self.right.min(other.right),
+            top: self.top.min(other.top),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub enum Color {
     Black,
     White,
 }
 
-pub struct Pixmap {
-    pub data: [[u8; WIDTH as usize]; HEIGHT as usize],
+/// A portrait image of W columns and H rows, non zero values are lit.
+pub struct Pixmap<const W: usize, const H: usize> {
+    pub data: [[u8; W]; H],
+}
+
+impl<const W: usize, const H: usize> Pixmap<W, H> {
+    pub fn width(&self) -> isize {
+        W as isize
+    }
+
+    pub fn height(&self) -> isize {
+        H as isize
+    }
 }
 
 pub struct AsciiFont {
     characters: [[u8; 6]; 256],
 }
 
+impl AsciiFont {
+    pub fn new() -> Self {
+        let mut characters = [[0u8; 6]; 256];
+        for (character, glyph) in GLYPHS {
+            characters[character as usize] = glyph;
+            characters[character.to_ascii_lowercase() as usize] = glyph;
+        }
+        AsciiFont { characters }
+    }
+
+    fn glyph(&self, character: char) -> &[u8; 6] {
+        match character.is_ascii() {
+            true => &self.characters[character as usize],
+            false => &self.characters[' ' as usize],
+        }
+    }
+}
+
 pub struct AsciiText {
     text: String,
     font: AsciiFont,
     bottom_left: Point,
     spacing: isize,
     invert: bool,
+    scale: isize,
+}
+
+impl AsciiText {
+    pub fn new(text: &str, bottom_left: Point, spacing: isize, invert: bool) -> Self {
+        AsciiText {
+            text: text.to_string(),
+            font: AsciiFont::new(),
+            bottom_left,
+            spacing,
+            invert,
+            scale: 1,
+        }
+    }
+
+    /// Draws every glyph pixel as a square of scale pixels, the spacing is
+    /// not scaled.
+    pub fn scaled(mut self, scale: isize) -> Self {
+        self.scale = isize::max(1, scale);
+        self
+    }
+
+    pub fn width(&self) -> isize {
+        text_width(&self.text, self.spacing, self.scale)
+    }
+}
+
+pub fn text_width(text: &str, spacing: isize, scale: isize) -> isize {
+    let length = text.chars().count() as isize;
+    match length {
+        0 => 0,
+        _ => length * GLYPH_WIDTH * scale + (length - 1) * spacing,
+    }
+}
+
+/// Brightness of the whole panel, independent of the contrast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Brightness {
+    /// The reduced brightness the panel starts with.
+    Dim,
+    Normal,
+}
+
+impl Brightness {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Brightness::Dim => "dim",
+            Brightness::Normal => "normal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Brightness> {
+        match name {
+            "dim" => Some(Brightness::Dim),
+            "normal" => Some(Brightness::Normal),
+            _ => None,
+        }
+    }
+}
+
+/// Clockwise turn of the image on the panel in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::R0),
+            90 => Ok(Rotation::R90),
+            180 => Ok(Rotation::R180),
+            270 => Ok(Rotation::R270),
+            _ => Err(format!("Unsupported rotation {}.", degrees)),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> u16 {
+        match rotation {
+            Rotation::R0 => 0,
+            Rotation::R90 => 90,
+            Rotation::R180 => 180,
+            Rotation::R270 => 270,
+        }
+    }
+}
+
+/// How the panel is mounted. Mirroring flips the image left to right
+/// before it is turned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    pub mirrored: bool,
+}
+
+impl Orientation {
+    pub fn new() -> Self {
+        Orientation {
+            rotation: Rotation::R0,
+            mirrored: false,
+        }
+    }
+
+    /// True if a quarter turn swaps the width and the height of the panel.
+    pub fn is_landscape(&self) -> bool {
+        matches!(self.rotation, Rotation::R90 | Rotation::R270)
+    }
 }
 
 pub trait DisplayControl {
@@ -36,70 +262,398 @@ pub trait DisplayControl {
     fn swap(&mut self);
     fn safe_swap(&mut self);
     fn fb(&mut self) -> &mut DisplayBuffer;
+    fn set_contrast(&mut self, contrast: u8);
+    fn set_brightness(&mut self, brightness: Brightness);
+    /// Shows lit pixels dark and dark pixels lit, without changing the buffer.
+    fn set_inverted(&mut self, inverted: bool);
+    /// Inverts the shown image on top of set_inverted, so an alert blinks
+    /// without sending the frame again. False if the panel can't, then the
+    /// caller has to invert the buffer itself.
+    fn set_alert(&mut self, alert: bool) -> bool {
+        false
+    }
+    /// Turns the panel off, the shown image is kept for the wake up.
+    fn sleep(&mut self);
+    fn wake(&mut self);
+    /// The caller redraws in the new width and height of the buffer.
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.fb().set_orientation(orientation);
+    }
 }
 
+// A display picked at start up is used like the concrete ones.
+impl<T: DisplayControl + ?Sized> DisplayControl for Box<T> {
+    fn init(&mut self) {
+        (**self).init()
+    }
+
+    fn deinit(&mut self) {
+        (**self).deinit()
+    }
+
+    fn swap(&mut self) {
+        (**self).swap()
+    }
+
+    fn safe_swap(&mut self) {
+        (**self).safe_swap()
+    }
+
+    fn fb(&mut self) -> &mut DisplayBuffer {
+        (**self).fb()
+    }
+
+    fn set_contrast(&mut self, contrast: u8) {
+        (**self).set_contrast(contrast)
+    }
+
+    fn set_brightness(&mut self, brightness: Brightness) {
+        (**self).set_brightness(brightness)
+    }
+
+    fn set_inverted(&mut self, inverted: bool) {
+        (**self).set_inverted(inverted)
+    }
+
+    fn set_alert(&mut self, alert: bool) -> bool {
+        (**self).set_alert(alert)
+    }
+
+    fn sleep(&mut self) {
+        (**self).sleep()
+    }
+
+    fn wake(&mut self) {
+        (**self).wake()
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        (**self).set_orientation(orientation)
+    }
+}
+
+/// The image in the memory layout of the panel. All drawing uses logical
+/// coordinates from the bottom left, the orientation maps them to the panel.
+/// Each byte holds 8 pixels along the panel width, one row of panel_width
+/// pixels after the other. Drawing only changes the pixels inside of the
+/// innermost clip rectangle, if one is pushed.
+#[derive(Clone)]
 pub struct DisplayBuffer {
-    pub buffer: [u8; DISPLAY_BUFFER_SIZE],
+    pub buffer: Vec<u8>,
+    panel_width: isize,
+    panel_height: isize,
+    orientation: Orientation,
+    clips: Vec<ClipRect>,
+    // The bytes the panel shows, None if they are unknown.
+    sent: Option<Vec<u8>>,
+}
+
+/// The changed part of the buffer, the rows and the bytes in each row
+/// included. On the SSD1305 family a panel row is a column of the controller
+/// and a byte of the row one of its pages.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct DirtyRegion {
+    pub first_row: isize,
+    pub last_row: isize,
+    pub first_byte: isize,
+    pub last_byte: isize,
 }
 
 impl DisplayBuffer {
-    pub fn new() -> Self {
+    /// A buffer for a panel in its portrait size, the width is a multiple of 8.
+    pub fn new(panel_width: isize, panel_height: isize) -> Self {
+        assert!(panel_width > 0 && panel_width % 8 == 0 && panel_height > 0);
         DisplayBuffer {
-            buffer: [0u8; DISPLAY_BUFFER_SIZE],
+            buffer: vec![0u8; (panel_width * panel_height / 8) as usize],
+            panel_width,
+            panel_height,
+            orientation: Orientation::new(),
+            clips: Vec::new(),
+            sent: None,
+        }
+    }
+
+    /// A black buffer of the same size and orientation, without clipping.
+    pub fn new_like(other: &DisplayBuffer) -> Self {
+        let mut buffer = DisplayBuffer::new(other.panel_width, other.panel_height);
+        buffer.orientation = other.orientation;
+        buffer
+    }
+
+    pub fn panel_width(&self) -> isize {
+        self.panel_width
+    }
+
+    pub fn panel_height(&self) -> isize {
+        self.panel_height
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Only changes the mapping, the buffer has to be redrawn.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    pub fn width(&self) -> isize {
+        match self.orientation.is_landscape() {
+            true => self.panel_height,
+            false => self.panel_width,
+        }
+    }
+
+    pub fn height(&self) -> isize {
+        match self.orientation.is_landscape() {
+            true => self.panel_width,
+            false => self.panel_height,
+        }
+    }
+
+    fn byte_index(&self, x: isize, y: isize) -> usize {
+        (y * (self.panel_width / 8) + x / 8) as usize
+    }
+
+    // The pixel of the panel at a point, None outside of the screen.
+    fn panel_position(&self, point: &Point) -> Option<(isize, isize)> {
+        let (width, height) = (self.width(), self.height());
+        if !(0..width).contains(&point.x) || !(0..height).contains(&point.y) {
+            return None;
         }
+        let x = match self.orientation.mirrored {
+            true => width - 1 - point.x,
+            false => point.x,
+        };
+        let y = point.y;
+        let (panel_width, panel_height) = (self.panel_width, self.panel_height);
+        Some(match self.orientation.rotation {
+            Rotation::R0 => (x, y),
+            Rotation::R90 => (y, panel_height - 1 - x),
+            Rotation::R180 => (panel_width - 1 - x, panel_height - 1 - y),
+            Rotation::R270 => (panel_width - 1 - y, x),
+        })
+    }
+
+    /// The part that changed since the last mark_clean, None if nothing did.
+    pub fn dirty_region(&self) -> Option<DirtyRegion> {
+        let bytes_per_row = self.panel_width / 8;
+        let sent = match &self.sent {
+            Some(sent) => sent,
+            None => {
+                return Some(DirtyRegion {
+                    first_row: 0,
+                    last_row: self.panel_height - 1,
+                    first_byte: 0,
+                    last_byte: bytes_per_row - 1,
+                })
+            }
+        };
+        let mut region: Option<DirtyRegion> = None;
+        for index in (0..self.buffer.len()).filter(|index| self.buffer[*index] != sent[*index]) {
+            let (row, byte) = (index as isize / bytes_per_row, index as isize % bytes_per_row);
+            region = Some(match region {
+                Some(region) => DirtyRegion {
+                    first_row: region.first_row.min(row),
+                    last_row: region.last_row.max(row),
+                    first_byte: region.first_byte.min(byte),
+                    last_byte: region.last_byte.max(byte),
+                },
+                None => DirtyRegion {
+                    first_row: row,
+                    last_row: row,
+                    first_byte: byte,
+                    last_byte: byte,
+                },
+            });
+        }
+        region
+    }
+
+    /// The bytes of the region, row after row.
+    pub fn region_data(&self, region: &DirtyRegion) -> Vec<u8> {
+        let bytes_per_row = self.panel_width / 8;
+        (region.first_row..=region.last_row)
+            .flat_map(|row| {
+                let start = (row * bytes_per_row + region.first_byte) as usize;
+                let end = (row * bytes_per_row + region.last_byte) as usize;
+                self.buffer[start..=end].iter().copied()
+            })
+            .collect()
+    }
+
+    /// The panel shows the buffer now.
+    pub fn mark_clean(&mut self) {
+        self.sent = Some(self.buffer.clone());
+    }
+
+    /// The panel content is unknown, e.g. after a reset, so all of the buffer
+    /// is dirty.
+    pub fn mark_dirty(&mut self) {
+        self.sent = None;
+    }
+
+    /// Limits drawing to the part of the rectangle inside of the current
+    /// clip, until it is popped again.
+    pub fn push_clip(&mut self, clip: ClipRect) {
+        let clip = match self.clip() {
+            Some(current) => current.intersection(&clip),
+            None => clip,
+        };
+        self.clips.push(clip);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    pub fn clip(&self) -> Option<ClipRect> {
+        self.clips.last().copied()
+    }
+
+    // The logical points inside of the clip, None if all of the screen is.
+    fn clipped_points(&self) -> Option<Vec<Point>> {
+        let clip = self.clip()?;
+        let mut points = Vec::new();
+        for y in clip.bottom.max(0)..=clip.top.min(self.height() - 1) {
+            for x in clip.left.max(0)..=clip.right.min(self.width() - 1) {
+                points.push(Point { x, y });
+            }
+        }
+        Some(points)
     }
 
     pub fn fill_with_black(&mut self) {
-        self.buffer = [0u8; DISPLAY_BUFFER_SIZE];
+        match self.clipped_points() {
+            Some(points) => points
+                .iter()
+                .for_each(|point| self.set_pixel_color(point, &Color::Black)),
+            None => self.buffer.fill(0u8),
+        }
     }
 
     pub fn fill_with_white(&mut self) {
-        self.buffer = [255u8; DISPLAY_BUFFER_SIZE];
+        match self.clipped_points() {
+            Some(points) => points
+                .iter()
+                .for_each(|point| self.set_pixel_color(point, &Color::White)),
+            None => self.buffer.fill(255u8),
+        }
     }
 
-    pub fn fill_with_pixmap(&mut self, pixmap: &Pixmap) {
-        let data = &pixmap.data;
-        for x in 0..WIDTH as usize {
-            for (y, data_row) in data.iter().enumerate().take(HEIGHT as usize) {
-                let pixel_color = if data_row[x] == 0 {
-                    Color::Black
-                } else {
-                    Color::White
+    /// Draws the portrait pixmap centered, with its first row at the top.
+    /// On a landscape screen it is turned counterclockwise to fill it.
+    pub fn fill_with_pixmap<const W: usize, const H: usize>(&mut self, pixmap: &Pixmap<W, H>) {
+        let is_landscape = self.width() > self.height();
+        let (width, height) = match is_landscape {
+            true => (self.height(), self.width()),
+            false => (self.width(), self.height()),
+        };
+        let left = ((width - pixmap.width()) / 2) as i32;
+        let top = ((height - pixmap.height()) / 2) as i32;
+        match is_landscape {
+            true => {
+                let bottom = self.height() as i32 - 1 - left;
+                let turned = pixmap.pixels().map(|Pixel(point, color)| {
+                    Pixel(GraphicsPoint::new(top + point.y, bottom - point.x), color)
+                });
+                self.draw_iter(turned).unwrap();
+            }
+            false => Image::new(pixmap, GraphicsPoint::new(left, top))
+                .draw(self)
+                .unwrap(),
+        }
+    }
+
+    pub fn invert(&mut self) {
+        match self.clipped_points() {
+            Some(points) => points.iter().for_each(|point| self.toggle_pixel(point)),
+            None => self.buffer.iter_mut().for_each(|byte| *byte = !*byte),
+        }
+    }
+
+    /// Moves the content by dx and dy pixels, uncovered pixels turn black.
+    pub fn shift(&mut self, dx: isize, dy: isize) {
+        let source = self.clone();
+        self.fill_with_black();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let from = Point {
+                    x: x - dx,
+                    y: y - dy,
                 };
-                self.set_pixel_color(
-                    &Point {
-                        x: x as isize,
-                        y: 127 - y as isize,
-                    },
-                    &pixel_color,
-                );
+                if source.get_pixel_color(&from) == Ok(Color::White) {
+                    self.set_pixel_color(&Point { x, y }, &Color::White);
+                }
+            }
+        }
+    }
+
+    pub fn write_ascii_text(&mut self, text: AsciiText) {
+        let (on_color, off_color) = match text.invert {
+            true => (Color::Black, Color::White),
+            false => (Color::White, Color::Black),
+        };
+        let scale = text.scale;
+        let mut x0 = text.bottom_left.x;
+        for character in text.text.chars() {
+            for (row, bits) in text.font.glyph(character).iter().enumerate() {
+                let y0 = text.bottom_left.y + (GLYPH_HEIGHT - 1 - row as isize) * scale;
+                for column in 0..GLYPH_WIDTH {
+                    let is_on = bits & (0b1000 >> column) != 0;
+                    let color = if is_on { &on_color } else { &off_color };
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            let x = x0 + column * scale + dx;
+                            self.set_pixel_color(&Point { x, y: y0 + dy }, color);
+                        }
+                    }
+                }
             }
+            x0 += GLYPH_WIDTH * scale + text.spacing;
         }
     }
 
-    pub fn write_ascii_text(&mut self, text: AsciiText) {}
+    /// Writes each word of the text on its own horizontally centered line,
+    /// the block of lines is vertically centered around center_y.
+    pub fn write_centered_text(&mut self, text: &str, center_y: isize) {
+        let lines: Vec<&str> = text.split_whitespace().collect();
+        let line_count = lines.len() as isize;
+        if line_count == 0 {
+            return;
+        }
+        let block_height = line_count * GLYPH_HEIGHT + (line_count - 1) * TEXT_LINE_SPACING;
+        let mut y = center_y + block_height / 2 - GLYPH_HEIGHT;
+        for line in lines {
+            let x = (self.width() - text_width(line, 1, 1)) / 2;
+            self.write_ascii_text(AsciiText::new(line, Point { x, y }, 1, false));
+            y -= GLYPH_HEIGHT + TEXT_LINE_SPACING;
+        }
+    }
 
     pub fn get_pixel_color(&self, point: &Point) -> Result<Color, &'static str> {
-        let byte = point.y * 4 + (point.x / 8);
-        if byte >= 0 && byte < self.buffer.len() as isize {
-            let byte_checked: usize = byte as usize;
-            let bit_mask = 1u8 << (point.x % 8);
-            match self.buffer[byte_checked] & bit_mask > 0 {
-                true => return Ok(Color::White),
-                false => return Ok(Color::Black),
+        match self.panel_position(point) {
+            Some((x, y)) => {
+                let bit_mask = 1u8 << (x % 8);
+                match self.buffer[self.byte_index(x, y)] & bit_mask > 0 {
+                    true => Ok(Color::White),
+                    false => Ok(Color::Black),
+                }
             }
+            None => Err("Pixel is outside of defined screen, cannot get pixel color."),
         }
-        Err("Pixel is outside of defined screen, cannot get pixel color.")
     }
 
     pub fn set_pixel_color(&mut self, point: &Point, color: &Color) {
-        let byte = point.y * 4 + (point.x / 8);
-        if byte >= 0 && byte < self.buffer.len() as isize {
-            let byte_checked: usize = byte as usize;
-            let bit_mask = 1u8 << (point.x % 8);
+        if self.clip().is_some_and(|clip| !clip.contains(point)) {
+            return;
+        }
+        if let Some((x, y)) = self.panel_position(point) {
+            let byte = self.byte_index(x, y);
+            let bit_mask = 1u8 << (x % 8);
             match color {
-                Color::White => self.buffer[byte_checked] |= bit_mask,
-                Color::Black => self.buffer[byte_checked] &= !bit_mask,
+                Color::White => self.buffer[byte] |= bit_mask,
+                Color::Black => self.buffer[byte] &= !bit_mask,
             }
         } else {
             // fail silently
@@ -212,11 +766,11 @@ mod tests {
 
     #[test]
     fn test_after_display_creation_buffer_is_black() {
-        let display = DisplayBuffer::new();
+        let display = DisplayBuffer::new(32, 128);
         let mut point: Point = Point { x: 0, y: 0 };
-        for x in 0..WIDTH {
+        for x in 0..display.width() {
             point.x = x;
-            for y in 0..HEIGHT {
+            for y in 0..display.height() {
                 point.y = y;
                 assert!(display.get_pixel_color(&point) == Ok(Color::Black));
             }
@@ -225,12 +779,12 @@ mod tests {
 
     #[test]
     fn test_after_fill_with_white_buffer_is_white() {
-        let mut display = DisplayBuffer::new();
+        let mut display = DisplayBuffer::new(32, 128);
         display.fill_with_white();
         let mut point: Point = Point { x: 0, y: 0 };
-        for x in 0..WIDTH {
+        for x in 0..display.width() {
             point.x = x;
-            for y in 0..HEIGHT {
+            for y in 0..display.height() {
                 point.y = y;
                 assert!(display.get_pixel_color(&point) == Ok(Color::White));
             }
@@ -239,13 +793,13 @@ mod tests {
 
     #[test]
     fn test_after_fill_with_black_buffer_is_all_black() {
-        let mut display = DisplayBuffer::new();
+        let mut display = DisplayBuffer::new(32, 128);
         display.fill_with_white();
         display.fill_with_black();
         let mut point: Point = Point { x: 0, y: 0 };
-        for x in 0..WIDTH {
+        for x in 0..display.width() {
             point.x = x;
-            for y in 0..HEIGHT {
+            for y in 0..display.height() {
                 point.y = y;
                 assert!(display.get_pixel_color(&point) == Ok(Color::Black));
             }
@@ -254,7 +808,7 @@ mod tests {
 
     #[test]
     fn test_set_and_get_of_pixels() {
-        let mut display = DisplayBuffer::new();
+        let mut display = DisplayBuffer::new(32, 128);
 
         let mut points = Vec::<Point>::new();
         points.push(Point { x: 0, y: 0 });
@@ -273,7 +827,7 @@ mod tests {
 
     #[test]
     fn test_pixel_toggle() {
-        let mut display = DisplayBuffer::new();
+        let mut display = DisplayBuffer::new(32, 128);
 
         let mut points = Vec::<Point>::new();
         points.push(Point { x: 0, y: 0 });
@@ -358,7 +912,7 @@ mod tests {
             true
         };
 
-        let mut display = DisplayBuffer::new();
+        let mut display = DisplayBuffer::new(32, 128);
 
         display.draw_box_with_points(
             &Point { x: 0, y: 0 },
@@ -389,4 +943,226 @@ mod tests {
         ));
         display.fill_with_black();
     }
+
+    #[test]
+    fn test_small_buffer_centers_the_pixmap() {
+        let mut display = DisplayBuffer::new(8, 16);
+        let pixmap = Pixmap {
+            data: [[1, 0], [0, 1]],
+        };
+
+        display.fill_with_pixmap(&pixmap);
+
+        assert_eq!(display.buffer.len(), 16);
+        assert_eq!(display.buffer.iter().map(|byte| byte.count_ones()).sum::<u32>(), 2);
+        assert!(display.get_pixel_color(&Point { x: 3, y: 8 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 4, y: 7 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 8, y: 0 }).is_err());
+        assert!(display.get_pixel_color(&Point { x: 0, y: 16 }).is_err());
+
+        // Turned counterclockwise on the landscape screen.
+        display.fill_with_black();
+        display.set_orientation(Orientation {
+            rotation: Rotation::R90,
+            mirrored: false,
+        });
+        display.fill_with_pixmap(&pixmap);
+        assert!(display.get_pixel_color(&Point { x: 7, y: 3 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 8, y: 4 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 8, y: 3 }) == Ok(Color::Black));
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut display = DisplayBuffer::new(32, 128);
+        display.set_pixel_color(&Point { x: 3, y: 5 }, &Color::White);
+
+        display.invert();
+
+        assert!(display.get_pixel_color(&Point { x: 3, y: 5 }) == Ok(Color::Black));
+        assert!(display.get_pixel_color(&Point { x: 4, y: 5 }) == Ok(Color::White));
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut display = DisplayBuffer::new(32, 128);
+        display.set_pixel_color(&Point { x: 7, y: 5 }, &Color::White);
+        display.set_pixel_color(&Point { x: 31, y: 0 }, &Color::White);
+
+        display.shift(1, -1);
+
+        assert!(display.get_pixel_color(&Point { x: 8, y: 4 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 7, y: 5 }) == Ok(Color::Black));
+        // Pixels shifted out of the screen are gone, not wrapped around.
+        assert_eq!(display.buffer.iter().map(|byte| byte.count_ones()).sum::<u32>(), 1);
+    }
+
+    fn is_panel_pixel_lit(display: &DisplayBuffer, x: usize, y: usize) -> bool {
+        display.buffer[y * 4 + x / 8] & (1u8 << (x % 8)) > 0
+    }
+
+    #[test]
+    fn test_rotation_maps_to_the_panel() {
+        let mut display = DisplayBuffer::new(32, 128);
+        let top_left = |display: &DisplayBuffer| Point {
+            x: 0,
+            y: display.height() - 1,
+        };
+
+        display.set_orientation(Orientation {
+            rotation: Rotation::R90,
+            mirrored: false,
+        });
+        assert_eq!((display.width(), display.height()), (128, 32));
+        display.set_pixel_color(&top_left(&display), &Color::White);
+        assert!(is_panel_pixel_lit(&display, 31, 127));
+
+        display.fill_with_black();
+        display.set_orientation(Orientation {
+            rotation: Rotation::R180,
+            mirrored: false,
+        });
+        display.set_pixel_color(&top_left(&display), &Color::White);
+        assert!(is_panel_pixel_lit(&display, 31, 0));
+
+        display.fill_with_black();
+        display.set_orientation(Orientation {
+            rotation: Rotation::R270,
+            mirrored: true,
+        });
+        display.set_pixel_color(&top_left(&display), &Color::White);
+        assert!(is_panel_pixel_lit(&display, 0, 127));
+        assert!(display.get_pixel_color(&Point { x: 128, y: 0 }).is_err());
+    }
+
+    #[test]
+    fn test_mirroring_flips_left_and_right() {
+        let mut display = DisplayBuffer::new(32, 128);
+        display.set_orientation(Orientation {
+            rotation: Rotation::R0,
+            mirrored: true,
+        });
+
+        display.set_pixel_color(&Point { x: 0, y: 5 }, &Color::White);
+
+        assert!(is_panel_pixel_lit(&display, 31, 5));
+        assert!(display.get_pixel_color(&Point { x: 0, y: 5 }) == Ok(Color::White));
+    }
+
+    #[test]
+    fn test_rotation_degrees() {
+        assert_eq!(Rotation::try_from(270), Ok(Rotation::R270));
+        assert!(Rotation::try_from(45).is_err());
+        assert_eq!(u16::from(Rotation::R90), 90);
+    }
+
+    #[test]
+    fn test_write_ascii_text() {
+        let mut display = DisplayBuffer::new(32, 128);
+
+        let text = AsciiText::new("l!", Point { x: 2, y: 10 }, 1, false);
+        assert_eq!(text.width(), 9);
+        display.write_ascii_text(text);
+
+        // Bottom row of the L is fully lit, the top row only on the left.
+        for x in 2..6 {
+            assert!(display.get_pixel_color(&Point { x, y: 10 }) == Ok(Color::White));
+        }
+        assert!(display.get_pixel_color(&Point { x: 2, y: 15 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 3, y: 15 }) == Ok(Color::Black));
+        // The exclamation mark has a gap above its dot.
+        assert!(display.get_pixel_color(&Point { x: 8, y: 10 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 8, y: 11 }) == Ok(Color::Black));
+        assert!(display.get_pixel_color(&Point { x: 8, y: 12 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 2, y: 16 }) == Ok(Color::Black));
+    }
+
+    #[test]
+    fn test_write_scaled_ascii_text() {
+        let mut display = DisplayBuffer::new(32, 128);
+
+        let text = AsciiText::new("1", Point { x: 0, y: 0 }, 1, false).scaled(2);
+        assert_eq!(text.width(), 8);
+        display.write_ascii_text(text);
+
+        // The foot of the 1 is three glyph pixels wide, six pixels scaled.
+        for x in 0..6 {
+            assert!(display.get_pixel_color(&Point { x, y: 0 }) == Ok(Color::White));
+            assert!(display.get_pixel_color(&Point { x, y: 1 }) == Ok(Color::White));
+        }
+        assert!(display.get_pixel_color(&Point { x: 6, y: 0 }) == Ok(Color::Black));
+        assert!(display.get_pixel_color(&Point { x: 2, y: 11 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 2, y: 12 }) == Ok(Color::Black));
+    }
+
+    #[test]
+    fn test_nested_clips_limit_drawing() {
+        let mut display = DisplayBuffer::new(8, 8);
+        let lit = |display: &DisplayBuffer| {
+            display.buffer.iter().map(|byte| byte.count_ones()).sum::<u32>()
+        };
+
+        display.push_clip(ClipRect {
+            left: 2,
+            bottom: 2,
+            right: 5,
+            top: 5,
+        });
+        display.push_clip(ClipRect {
+            left: 4,
+            bottom: -3,
+            right: 9,
+            top: 3,
+        });
+        display.fill_with_white();
+        assert_eq!(lit(&display), 4);
+        assert!(display.get_pixel_color(&Point { x: 4, y: 2 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 5, y: 3 }) == Ok(Color::White));
+
+        display.pop_clip();
+        display.set_pixel_color(&Point { x: 2, y: 5 }, &Color::White);
+        display.set_pixel_color(&Point { x: 1, y: 5 }, &Color::White);
+        assert_eq!(lit(&display), 5);
+
+        display.pop_clip();
+        display.set_pixel_color(&Point { x: 1, y: 5 }, &Color::White);
+        assert_eq!(lit(&display), 6);
+    }
+
+    #[test]
+    fn test_dirty_region_covers_the_changes_since_marked_clean() {
+        let mut display = DisplayBuffer::new(32, 128);
+        let all = DirtyRegion {
+            first_row: 0,
+            last_row: 127,
+            first_byte: 0,
+            last_byte: 3,
+        };
+        assert_eq!(display.dirty_region(), Some(all));
+
+        display.mark_clean();
+        assert_eq!(display.dirty_region(), None);
+        display.set_pixel_color(&Point { x: 9, y: 20 }, &Color::White);
+        display.set_pixel_color(&Point { x: 31, y: 5 }, &Color::White);
+        let region = display.dirty_region().unwrap();
+        assert_eq!(
+            region,
+            DirtyRegion {
+                first_row: 5,
+                last_row: 20,
+                first_byte: 1,
+                last_byte: 3,
+            }
+        );
+        let data = display.region_data(&region);
+        assert_eq!(data.len(), 16 * 3);
+        assert_eq!((data[2], data[45]), (0x80, 0x02));
+
+        // Drawing the same again changes nothing.
+        display.mark_clean();
+        display.set_pixel_color(&Point { x: 9, y: 20 }, &Color::White);
+        assert_eq!(display.dirty_region(), None);
+        display.mark_dirty();
+        assert_eq!(display.dirty_region(), Some(all));
+    }
 }
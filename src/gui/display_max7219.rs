@@ -0,0 +1,229 @@
+use crate::gui::display_bus::SpiInterface;
+use crate::gui::display_control::{Brightness, DisplayBuffer, DisplayControl, DEFAULT_CONTRAST};
+#[cfg(target_arch = "arm")]
+use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
+
+const REGISTER_DECODE_MODE: u8 = 0x09;
+const REGISTER_INTENSITY: u8 = 0x0A;
+const REGISTER_SCAN_LIMIT: u8 = 0x0B;
+const REGISTER_SHUTDOWN: u8 = 0x0C;
+const REGISTER_DISPLAY_TEST: u8 = 0x0F;
+
+/// A chain of 8x8 LED matrix modules. The first module in the chain shows
+/// the bottom of the portrait buffer, each byte of the buffer is one matrix
+/// column with bit 0 at the top row. LEDs can't invert, so inversion and
+/// alerts are done on the data sent.
+pub struct Max7219Display<T> {
+    fb: DisplayBuffer,
+    pub spi: T,
+    modules: usize,
+    contrast: u8,
+    brightness: Brightness,
+    inverted: bool,
+}
+
+#[cfg(target_arch = "arm")]
+impl Max7219Display<Spi> {
+    pub fn new(modules: usize) -> Self {
+        let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 1_000_000, Mode::Mode0).unwrap();
+        Max7219Display::new_generic(spi, modules)
+    }
+}
+
+impl<T: SpiInterface> Max7219Display<T> {
+    pub fn new_generic(spi: T, modules: usize) -> Self {
+        let modules = modules.max(1);
+        Max7219Display {
+            fb: DisplayBuffer::new(8, 8 * modules as isize),
+            spi,
+            modules,
+            contrast: DEFAULT_CONTRAST,
+            brightness: Brightness::Dim,
+            inverted: false,
+        }
+    }
+
+    // Every module latches its own register and value, the first bytes
+    // shifted in end up in the last module.
+    fn send_register(&mut self, register: u8, values: &[u8]) {
+        let mut bytes = Vec::with_capacity(2 * values.len());
+        for value in values.iter().rev() {
+            bytes.push(register);
+            bytes.push(*value);
+        }
+        self.spi.send_bytes(&bytes);
+    }
+
+    fn send_to_all(&mut self, register: u8, value: u8) {
+        let values = vec![value; self.modules];
+        self.send_register(register, &values);
+    }
+
+    // The contrast maps to the 16 intensity steps, dim halves them.
+    fn send_intensity(&mut self) {
+        let intensity = match self.brightness {
+            Brightness::Dim => self.contrast >> 5,
+            Brightness::Normal => self.contrast >> 4,
+        };
+        self.send_to_all(REGISTER_INTENSITY, intensity);
+    }
+
+    // The digit register of a row holds the row with bit 7 at the left.
+    fn row_values(&self, row: usize) -> Vec<u8> {
+        (0..self.modules)
+            .map(|module| {
+                let columns = &self.fb.buffer[8 * module..8 * module + 8];
+                let value = columns
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, column)| *column & (1u8 << row) != 0)
+                    .fold(0u8, |value, (column, _)| value | (0x80 >> column));
+                match self.inverted {
+                    true => !value,
+                    false => value,
+                }
+            })
+            .collect()
+    }
+}
+
+impl<T: SpiInterface> DisplayControl for Max7219Display<T> {
+    fn init(&mut self) {
+        // Based on https://datasheets.maximintegrated.com/en/ds/MAX7219-MAX7221.pdf
+        self.send_to_all(REGISTER_DISPLAY_TEST, 0x00);
+        self.send_to_all(REGISTER_SCAN_LIMIT, 0x07); // Show all 8 rows
+        self.send_to_all(REGISTER_DECODE_MODE, 0x00); // No BCD decoding
+        self.send_intensity();
+        self.send_to_all(REGISTER_SHUTDOWN, 0x01); // Normal operation
+        self.fb.mark_dirty();
+        self.swap();
+    }
+
+    fn deinit(&mut self) {
+        self.fb.fill_with_black();
+        self.swap();
+        self.sleep();
+    }
+
+    // A register spans all modules, so any change sends all rows.
+    fn swap(&mut self) {
+        if self.fb.dirty_region().is_none() {
+            return;
+        }
+        for row in 0..8 {
+            let values = self.row_values(row);
+            self.send_register(row as u8 + 1, &values);
+        }
+        self.fb.mark_clean();
+    }
+
+    fn safe_swap(&mut self) {
+        self.swap(); // No double buffering, one swap is sufficient
+    }
+
+    fn fb(&mut self) -> &mut DisplayBuffer {
+        &mut self.fb
+    }
+
+    fn set_contrast(&mut self, contrast: u8) {
+        self.contrast = contrast;
+        self.send_intensity();
+    }
+
+    fn set_brightness(&mut self, brightness: Brightness) {
+        self.brightness = brightness;
+        self.send_intensity();
+    }
+
+    fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+        self.fb.mark_dirty();
+        self.swap();
+    }
+
+    fn sleep(&mut self) {
+        self.send_to_all(REGISTER_SHUTDOWN, 0x00);
+    }
+
+    fn wake(&mut self) {
+        self.send_to_all(REGISTER_SHUTDOWN, 0x01);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::display_bus::MockSpiInterface;
+    use crate::gui::display_control::{Color, Orientation, Point, Rotation};
+    use mockall::*;
+
+    fn set_send_bytes_expectation(
+        bytes: Vec<u8>,
+        spi: &mut MockSpiInterface,
+        sequence: &mut Sequence,
+    ) {
+        spi.expect_send_bytes()
+            .withf(move |send_bytes: &[u8]| send_bytes == bytes)
+            .return_const(())
+            .times(1)
+            .in_sequence(sequence);
+    }
+
+    #[test]
+    fn test_init_sequence() {
+        let mut display = Max7219Display::new_generic(MockSpiInterface::new(), 2);
+
+        let mut sequence = Sequence::new();
+        let commands: Vec<Vec<u8>> = vec![
+            vec![0x0F, 0x00, 0x0F, 0x00],
+            vec![0x0B, 0x07, 0x0B, 0x07],
+            vec![0x09, 0x00, 0x09, 0x00],
+            vec![0x0A, 0x04, 0x0A, 0x04],
+            vec![0x0C, 0x01, 0x0C, 0x01],
+        ];
+        for command in commands {
+            set_send_bytes_expectation(command, &mut display.spi, &mut sequence);
+        }
+        for row in 1..=8 {
+            set_send_bytes_expectation(vec![row, 0, row, 0], &mut display.spi, &mut sequence);
+        }
+
+        display.init();
+    }
+
+    #[test]
+    fn test_swap_sends_the_last_module_first() {
+        let mut display = Max7219Display::new_generic(MockSpiInterface::new(), 2);
+        // A landscape strip, its top left pixel is the first LED of the chain.
+        display.fb().set_orientation(Orientation {
+            rotation: Rotation::R270,
+            mirrored: false,
+        });
+        display
+            .fb()
+            .set_pixel_color(&Point { x: 0, y: 7 }, &Color::White);
+
+        let mut sequence = Sequence::new();
+        set_send_bytes_expectation(vec![1, 0, 1, 0x80], &mut display.spi, &mut sequence);
+        for row in 2..=8 {
+            set_send_bytes_expectation(vec![row, 0, row, 0], &mut display.spi, &mut sequence);
+        }
+
+        display.swap();
+    }
+
+    #[test]
+    fn test_unchanged_frame_is_skipped_until_inverted() {
+        let mut display = Max7219Display::new_generic(MockSpiInterface::new(), 1);
+        display.fb().mark_clean();
+
+        let mut sequence = Sequence::new();
+        for row in 1..=8 {
+            set_send_bytes_expectation(vec![row, 0xFF], &mut display.spi, &mut sequence);
+        }
+
+        display.swap();
+        display.set_inverted(true);
+        display.swap();
+    }
+}
@@ -1,4 +1,6 @@
-use crate::gui::display_control::{Color, DisplayBuffer, DisplayControl, Point, HEIGHT, WIDTH};
+use crate::gui::display_control::{
+    Brightness, Color, DisplayBuffer, DisplayControl, Orientation, Point, DEFAULT_CONTRAST,
+};
 use minifb::{Key, Scale, Window, WindowOptions};
 use std::convert::TryFrom;
 
@@ -7,28 +9,46 @@ const BLACK: u32 = 0x00000000u32;
 
 pub struct MiniFbDisplay {
     fb: DisplayBuffer,
-    buffer: [u32; 32 * 128],
+    buffer: Vec<u32>,
     window: Window,
+    contrast: u8,
+    brightness: Brightness,
+    inverted: bool,
+    alert: bool,
+    sleeping: bool,
+}
+
+// The window shows the screen as seen by the user, in the logical width
+// and height of the buffer.
+fn create_window(fb: &DisplayBuffer) -> Window {
+    Window::new(
+        "Hourglass",
+        fb.width() as usize,
+        fb.height() as usize,
+        WindowOptions {
+            resize: false,
+            scale: Scale::X4,
+            borderless: false,
+            title: false,
+            ..WindowOptions::default()
+        },
+    )
+    .expect("Unable to create window")
 }
 
 impl MiniFbDisplay {
-    pub fn new() -> Self {
+    /// Simulates a panel of the size, in its portrait width and height.
+    pub fn new(panel_width: isize, panel_height: isize) -> Self {
+        let fb = DisplayBuffer::new(panel_width, panel_height);
         MiniFbDisplay {
-            fb: DisplayBuffer::new(),
-            buffer: [0u32; WIDTH as usize * HEIGHT as usize],
-            window: Window::new(
-                "Hourglass",
-                WIDTH as usize,
-                HEIGHT as usize,
-                WindowOptions {
-                    resize: false,
-                    scale: Scale::X4,
-                    borderless: false,
-                    title: false,
-                    ..WindowOptions::default()
-                },
-            )
-            .expect("Unable to create window"),
+            window: create_window(&fb),
+            buffer: vec![0u32; fb.buffer.len() * 8],
+            fb,
+            contrast: DEFAULT_CONTRAST,
+            brightness: Brightness::Dim,
+            inverted: false,
+            alert: false,
+            sleeping: false,
         }
     }
 }
@@ -43,20 +63,33 @@ impl DisplayControl for MiniFbDisplay {
     }
 
     fn swap(&mut self) {
+        // Simulate the contrast by dimming white, the default contrast is
+        // full white in dim mode and the normal brightness doubles it.
+        let contrast = match self.brightness {
+            Brightness::Dim => self.contrast as u32,
+            Brightness::Normal => self.contrast as u32 * 2,
+        };
+        let level = u32::min(255, contrast * 255 / DEFAULT_CONTRAST as u32);
+        let white = (level << 16) | (level << 8) | level;
+        let (width, height) = (self.fb.width(), self.fb.height());
         for i in 0..self.buffer.len() {
             let i_isize = isize::try_from(i).unwrap();
             let point = Point {
-                x: i_isize % 32,
-                y: 127 - i_isize / 32,
+                x: i_isize % width,
+                y: height - 1 - i_isize / width,
             };
-            self.buffer[i] = match self.fb.get_pixel_color(&point) {
-                Ok(Color::White) => WHITE,
-                Ok(Color::Black) => BLACK,
+            let is_lit = match self.fb.get_pixel_color(&point) {
+                Ok(color) => (color == Color::White) != (self.inverted != self.alert),
                 Err(s) => panic!("Unable to get pixel color {}", s),
+            };
+            // A sleeping panel shows nothing.
+            self.buffer[i] = match is_lit && !self.sleeping {
+                true => white,
+                false => BLACK,
             }
         }
         self.window
-            .update_with_buffer(&self.buffer, 32, 127)
+            .update_with_buffer(&self.buffer, width as usize, height as usize)
             .unwrap();
     }
 
@@ -68,4 +101,40 @@ impl DisplayControl for MiniFbDisplay {
     fn fb(&mut self) -> &mut DisplayBuffer {
         &mut self.fb
     }
+
+    fn set_contrast(&mut self, contrast: u8) {
+        self.contrast = contrast;
+        self.swap();
+    }
+
+    fn set_brightness(&mut self, brightness: Brightness) {
+        self.brightness = brightness;
+        self.swap();
+    }
+
+    fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+        self.swap();
+    }
+
+    fn set_alert(&mut self, alert: bool) -> bool {
+        self.alert = alert;
+        self.swap();
+        true
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.fb.set_orientation(orientation);
+        self.window = create_window(&self.fb);
+    }
+
+    fn sleep(&mut self) {
+        self.sleeping = true;
+        self.swap();
+    }
+
+    fn wake(&mut self) {
+        self.sleeping = false;
+        self.swap();
+    }
 }
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[cfg(target_arch = "arm")]
+use crate::gui::display_control::DisplayControl;
+#[cfg(target_arch = "arm")]
+use crate::gui::display_max7219::Max7219Display;
+#[cfg(target_arch = "arm")]
+use crate::gui::display_oled::{OledController, OledDisplay};
+#[cfg(target_arch = "arm")]
+use crate::gui::display_raspberry::RaspberryDisplay;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BusKind {
+    Spi,
+    I2c,
+}
+
+/// The display controller in the config, it is picked at start up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "controller", rename_all = "lowercase")]
+pub enum DisplayModel {
+    /// The 128x32 panel of the Waveshare HAT.
+    Ssd1305,
+    /// 128x32 or, with 64 rows, 128x64.
+    Ssd1306 {
+        #[serde(deserialize_with = "deserialize_ssd1306_rows")]
+        rows: u8,
+        bus: BusKind,
+    },
+    /// 128x64.
+    Sh1106 { bus: BusKind },
+    /// A chain of 8x8 LED matrix modules.
+    Max7219 { modules: u8 },
+}
+
+// Other rows would drive the panel with the wrong geometry.
+fn deserialize_ssd1306_rows<'de, D>(deserializer: D) -> Result<u8, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match u8::deserialize(deserializer)? {
+        rows @ (32 | 64) => Ok(rows),
+        rows => Err(serde::de::Error::custom(format!(
+            "The SSD1306 has 32 or 64 rows, not {}.",
+            rows
+        ))),
+    }
+}
+
+impl DisplayModel {
+    pub fn new() -> Self {
+        DisplayModel::Ssd1305
+    }
+
+    /// Width and height of the panel in portrait, as its buffer is laid out.
+    pub fn panel_size(&self) -> (isize, isize) {
+        match self {
+            DisplayModel::Ssd1305 => (32, 128),
+            DisplayModel::Ssd1306 { rows: 32, .. } => (32, 128),
+            DisplayModel::Ssd1306 { .. } | DisplayModel::Sh1106 { .. } => (64, 128),
+            DisplayModel::Max7219 { modules } => (8, 8 * (*modules).max(1) as isize),
+        }
+    }
+
+    #[cfg(target_arch = "arm")]
+    pub fn create_display(&self) -> Box<dyn DisplayControl> {
+        let rows = self.panel_size().0 as u8;
+        match *self {
+            DisplayModel::Ssd1305 => Box::new(RaspberryDisplay::new()),
+            DisplayModel::Ssd1306 { bus, .. } => create_oled(OledController::Ssd1306 { rows }, bus),
+            DisplayModel::Sh1106 { bus } => create_oled(OledController::Sh1106, bus),
+            DisplayModel::Max7219 { modules } => Box::new(Max7219Display::new(modules as usize)),
+        }
+    }
+}
+
+#[cfg(target_arch = "arm")]
+fn create_oled(controller: OledController, bus: BusKind) -> Box<dyn DisplayControl> {
+    match bus {
+        BusKind::Spi => Box::new(OledDisplay::with_spi(controller)),
+        BusKind::I2c => Box::new(OledDisplay::with_i2c(controller)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_from_config() {
+        let model: DisplayModel =
+            serde_json::from_str(r#"{"controller": "ssd1306", "rows": 64, "bus": "i2c"}"#).unwrap();
+
+        assert_eq!(
+            model,
+            DisplayModel::Ssd1306 {
+                rows: 64,
+                bus: BusKind::I2c
+            }
+        );
+        assert_eq!(model.panel_size(), (64, 128));
+        assert_eq!(DisplayModel::Max7219 { modules: 4 }.panel_size(), (8, 32));
+        assert!(serde_json::from_str::<DisplayModel>(
+            r#"{"controller": "ssd1306", "rows": 48, "bus": "spi"}"#
+        )
+        .is_err());
+        assert_eq!(
+            serde_json::to_string(&DisplayModel::new()).unwrap(),
+            r#"{"controller":"ssd1305"}"#
+        );
+    }
+}
@@ -0,0 +1,423 @@
+#[cfg(target_arch = "arm")]
+use crate::gui::display_bus::{I2cBus, SpiBus, OLED_I2C_ADDRESS};
+use crate::gui::display_bus::{OledBus, PinInterface};
+use crate::gui::display_control::{Brightness, DirtyRegion, DisplayBuffer, DisplayControl};
+#[cfg(target_arch = "arm")]
+use rppal::{
+    gpio::{Gpio, OutputPin},
+    i2c::I2c,
+    spi::{Bus, Mode, SlaveSelect, Spi},
+};
+use std::{thread, time};
+
+/// The 128 column OLED controllers next to the SSD1305.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OledController {
+    /// 32 or 64 rows.
+    Ssd1306 { rows: u8 },
+    /// Always 64 rows, its 132 columns RAM shows the columns 2 to 129.
+    Sh1106,
+}
+
+impl OledController {
+    pub fn rows(&self) -> u8 {
+        match self {
+            OledController::Ssd1306 { rows } => *rows,
+            OledController::Sh1106 => 64,
+        }
+    }
+
+    fn pages(&self) -> u8 {
+        self.rows() / 8
+    }
+}
+
+/// SSD1306 or SH1106 panel of 128 columns, wired like the SSD1305 so the
+/// rows are the width of the buffer. Neither has a dim mode, only the
+/// contrast changes the brightness.
+pub struct OledDisplay<B, U> {
+    fb: DisplayBuffer,
+    controller: OledController,
+    pub bus: B,
+    reset_pin: Option<U>,
+    inverted: bool,
+    alert: bool,
+}
+
+#[cfg(target_arch = "arm")]
+impl OledDisplay<SpiBus<Spi, OutputPin>, OutputPin> {
+    /// Uses the pins of the SSD1305 HAT.
+    pub fn with_spi(controller: OledController) -> Self {
+        let bus = SpiBus {
+            spi: Spi::new(Bus::Spi0, SlaveSelect::Ss0, 8_000_000, Mode::Mode0).unwrap(),
+            dc_pin: Gpio::new().unwrap().get(24).unwrap().into_output(),
+        };
+        let reset_pin = Gpio::new().unwrap().get(25).unwrap().into_output();
+        OledDisplay::new_generic(controller, bus, Some(reset_pin))
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl OledDisplay<I2cBus<I2c>, OutputPin> {
+    pub fn with_i2c(controller: OledController) -> Self {
+        let mut i2c = I2c::new().unwrap();
+        i2c.set_slave_address(OLED_I2C_ADDRESS).unwrap();
+        OledDisplay::new_generic(controller, I2cBus { i2c }, None)
+    }
+}
+
+impl<B: OledBus, U: PinInterface> OledDisplay<B, U> {
+    pub fn new_generic(controller: OledController, bus: B, reset_pin: Option<U>) -> Self {
+        OledDisplay {
+            fb: DisplayBuffer::new(controller.rows() as isize, 128),
+            controller,
+            bus,
+            reset_pin,
+            inverted: false,
+            alert: false,
+        }
+    }
+
+    // An alert inverts the inverted display back to normal.
+    fn send_inversion(&mut self) {
+        match self.inverted != self.alert {
+            true => self.bus.send_commands(&[0xA7]), // Set Inverse Display
+            false => self.bus.send_commands(&[0xA6]), // Set Normal Display
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Some(reset_pin) = self.reset_pin.as_mut() {
+            let interval = time::Duration::from_millis(10);
+            reset_pin.set_pin(true);
+            thread::sleep(interval);
+            reset_pin.set_pin(false);
+            thread::sleep(interval);
+            reset_pin.set_pin(true);
+            thread::sleep(interval);
+        }
+    }
+}
+
+impl<B: OledBus, U: PinInterface> DisplayControl for OledDisplay<B, U> {
+    fn init(&mut self) {
+        let rows = self.controller.rows();
+        self.reset();
+        self.bus.send_commands(&[0xAE]); // Display OFF (sleep mode)
+        self.bus.send_commands(&[0xD5, 0x80]); // Set Display Clock Divide Ratio
+        self.bus.send_commands(&[0xA8, rows - 1]); // Set Multiplex Ratio
+        self.bus.send_commands(&[0xD3, 0x00]); // Set Display Offset
+        self.bus.send_commands(&[0x40]); // Set Display Start Line to 0
+        match self.controller {
+            OledController::Ssd1306 { .. } => {
+                // Based on https://cdn-shop.adafruit.com/datasheets/SSD1306.pdf
+                self.bus.send_commands(&[0x8D, 0x14]); // Enable Charge Pump
+                self.bus.send_commands(&[0x20, 0x01]); // Set Vertical Addressing Mode
+                let com_pins = if rows == 64 { 0x12 } else { 0x02 };
+                self.bus.send_commands(&[0xDA, com_pins]); // Set COM Pins Configuration
+                self.bus.send_commands(&[0xD9, 0xF1]); // Set Pre-charge Period
+                self.bus.send_commands(&[0xDB, 0x40]); // Set VCOMH Deselect Level
+                self.bus.send_commands(&[0x21, 0x00, 0x7F]); // Set Column Address range
+                let last_page = self.controller.pages() - 1;
+                self.bus.send_commands(&[0x22, 0x00, last_page]); // Set Page Address range
+            }
+            OledController::Sh1106 => {
+                // Based on the SH1106 datasheet, V2.3
+                self.bus.send_commands(&[0xAD, 0x8B]); // Enable DC-DC Converter
+                self.bus.send_commands(&[0xDA, 0x12]); // Set COM Pins Configuration
+                self.bus.send_commands(&[0xD9, 0x22]); // Set Pre-charge Period
+                self.bus.send_commands(&[0xDB, 0x35]); // Set VCOM Deselect Level
+            }
+        }
+        self.bus.send_commands(&[0xA4]); // Display follows the RAM content
+        self.send_inversion();
+        self.bus.send_commands(&[0xAF]); // Display ON
+        self.fb.mark_dirty(); // The reset left the display RAM unknown
+        self.swap();
+    }
+
+    fn deinit(&mut self) {
+        self.fb.fill_with_black();
+        self.swap();
+        self.sleep();
+        if let Some(reset_pin) = self.reset_pin.as_mut() {
+            reset_pin.set_pin(false);
+        }
+    }
+
+    // Only the changed columns and pages are sent, nothing if none changed.
+    fn swap(&mut self) {
+        let region = match self.fb.dirty_region() {
+            Some(region) => region,
+            None => return,
+        };
+        let (first_column, last_column) = (region.first_row as u8, region.last_row as u8);
+        let (first_page, last_page) = (region.first_byte as u8, region.last_byte as u8);
+        match self.controller {
+            OledController::Ssd1306 { .. } => {
+                let columns = [0x21, first_column, last_column];
+                self.bus.send_commands(&columns); // Set Column Address range
+                self.bus.send_commands(&[0x22, first_page, last_page]); // Set Page Address range
+                self.bus.send_data(&self.fb.region_data(&region));
+            }
+            OledController::Sh1106 => {
+                // Only page addressing, the region is sent page by page.
+                let column = first_column + 2;
+                for page in first_page..=last_page {
+                    let page_region = DirtyRegion {
+                        first_byte: page as isize,
+                        last_byte: page as isize,
+                        ..region
+                    };
+                    // Set the Page Address and the Column Address
+                    self.bus
+                        .send_commands(&[0xB0 | page, column & 0x0F, 0x10 | (column >> 4)]);
+                    self.bus.send_data(&self.fb.region_data(&page_region));
+                }
+            }
+        }
+        self.fb.mark_clean();
+    }
+
+    fn safe_swap(&mut self) {
+        self.swap(); // No double buffering, one swap is sufficient
+    }
+
+    fn fb(&mut self) -> &mut DisplayBuffer {
+        &mut self.fb
+    }
+
+    fn set_contrast(&mut self, contrast: u8) {
+        self.bus.send_commands(&[0x81, contrast]); // Set Contrast Control
+    }
+
+    fn set_brightness(&mut self, brightness: Brightness) {
+        // No dim mode on these controllers.
+    }
+
+    fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+        self.send_inversion();
+    }
+
+    fn set_alert(&mut self, alert: bool) -> bool {
+        self.alert = alert;
+        self.send_inversion();
+        true
+    }
+
+    fn sleep(&mut self) {
+        self.bus.send_commands(&[0xAE]); // Display OFF (sleep mode)
+    }
+
+    fn wake(&mut self) {
+        self.bus.send_commands(&[0xAF]); // Display ON
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::display_bus::{
+        I2cBus, MockI2cInterface, MockPinInterface, MockSpiInterface, SpiBus,
+    };
+    use mockall::predicate::*;
+    use mockall::*;
+
+    type SpiOled = OledDisplay<SpiBus<MockSpiInterface, MockPinInterface>, MockPinInterface>;
+    type I2cOled = OledDisplay<I2cBus<MockI2cInterface>, MockPinInterface>;
+
+    fn get_new_spi_display(controller: OledController) -> SpiOled {
+        let bus = SpiBus {
+            spi: MockSpiInterface::new(),
+            dc_pin: MockPinInterface::new(),
+        };
+        OledDisplay::new_generic(controller, bus, Some(MockPinInterface::new()))
+    }
+
+    fn get_new_i2c_display(controller: OledController) -> I2cOled {
+        let bus = I2cBus {
+            i2c: MockI2cInterface::new(),
+        };
+        OledDisplay::new_generic(controller, bus, None)
+    }
+
+    fn set_pin_expectation(value: bool, pin: &mut MockPinInterface, sequence: &mut Sequence) {
+        pin.expect_set_pin()
+            .with(eq(value))
+            .return_const(())
+            .times(1)
+            .in_sequence(sequence);
+    }
+
+    fn set_spi_expectation(
+        is_data: bool,
+        bytes: Vec<u8>,
+        display: &mut SpiOled,
+        sequence: &mut Sequence,
+    ) {
+        set_pin_expectation(is_data, &mut display.bus.dc_pin, sequence);
+        display
+            .bus
+            .spi
+            .expect_send_bytes()
+            .withf(move |send_bytes: &[u8]| send_bytes == bytes)
+            .return_const(())
+            .times(1)
+            .in_sequence(sequence);
+    }
+
+    fn set_i2c_expectation(bytes: Vec<u8>, display: &mut I2cOled, sequence: &mut Sequence) {
+        display
+            .bus
+            .i2c
+            .expect_send_bytes()
+            .withf(move |send_bytes: &[u8]| send_bytes == bytes)
+            .return_const(())
+            .times(1)
+            .in_sequence(sequence);
+    }
+
+    #[test]
+    fn test_ssd1306_spi_init_sequence() {
+        let mut display = get_new_spi_display(OledController::Ssd1306 { rows: 32 });
+
+        let mut sequence = Sequence::new();
+        let reset_pin = display.reset_pin.as_mut().unwrap();
+        set_pin_expectation(true, reset_pin, &mut sequence);
+        set_pin_expectation(false, reset_pin, &mut sequence);
+        set_pin_expectation(true, reset_pin, &mut sequence);
+        let commands: Vec<Vec<u8>> = vec![
+            vec![0xAE],
+            vec![0xD5, 0x80],
+            vec![0xA8, 0x1F],
+            vec![0xD3, 0x00],
+            vec![0x40],
+            vec![0x8D, 0x14],
+            vec![0x20, 0x01],
+            vec![0xDA, 0x02],
+            vec![0xD9, 0xF1],
+            vec![0xDB, 0x40],
+            vec![0x21, 0x00, 0x7F],
+            vec![0x22, 0x00, 0x03],
+            vec![0xA4],
+            vec![0xA6],
+            vec![0xAF],
+        ];
+        for command in commands {
+            set_spi_expectation(false, command, &mut display, &mut sequence);
+        }
+        set_spi_expectation(false, vec![0x21, 0x00, 0x7F], &mut display, &mut sequence);
+        set_spi_expectation(false, vec![0x22, 0x00, 0x03], &mut display, &mut sequence);
+        set_spi_expectation(true, vec![0u8; 512], &mut display, &mut sequence);
+
+        display.init();
+    }
+
+    #[test]
+    fn test_ssd1306_i2c_init_sequence() {
+        let mut display = get_new_i2c_display(OledController::Ssd1306 { rows: 64 });
+
+        let mut sequence = Sequence::new();
+        let commands: Vec<Vec<u8>> = vec![
+            vec![0xAE],
+            vec![0xD5, 0x80],
+            vec![0xA8, 0x3F],
+            vec![0xD3, 0x00],
+            vec![0x40],
+            vec![0x8D, 0x14],
+            vec![0x20, 0x01],
+            vec![0xDA, 0x12],
+            vec![0xD9, 0xF1],
+            vec![0xDB, 0x40],
+            vec![0x21, 0x00, 0x7F],
+            vec![0x22, 0x00, 0x07],
+            vec![0xA4],
+            vec![0xA6],
+            vec![0xAF],
+            vec![0x21, 0x00, 0x7F],
+            vec![0x22, 0x00, 0x07],
+        ];
+        for command in commands {
+            let mut bytes = vec![0x00];
+            bytes.extend(command);
+            set_i2c_expectation(bytes, &mut display, &mut sequence);
+        }
+        let mut data = vec![0x40];
+        data.extend(vec![0u8; 1024]);
+        set_i2c_expectation(data, &mut display, &mut sequence);
+
+        display.init();
+    }
+
+    #[test]
+    fn test_sh1106_swap_sends_pages() {
+        let mut display = get_new_spi_display(OledController::Sh1106);
+        // Column 1 lights the bottom row of page 7.
+        display.fb.buffer[8 + 7] = 0x80;
+
+        let mut sequence = Sequence::new();
+        for page in 0..8u8 {
+            set_spi_expectation(
+                false,
+                vec![0xB0 | page, 0x02, 0x10],
+                &mut display,
+                &mut sequence,
+            );
+            let mut data = vec![0u8; 128];
+            if page == 7 {
+                data[1] = 0x80;
+            }
+            set_spi_expectation(true, data, &mut display, &mut sequence);
+        }
+
+        display.swap();
+    }
+
+    #[test]
+    fn test_swap_sends_only_the_changed_region() {
+        let mut display = get_new_spi_display(OledController::Ssd1306 { rows: 32 });
+        display.fb.mark_clean();
+        display.fb.buffer[40 * 4 + 2] = 0x10;
+
+        let mut sequence = Sequence::new();
+        set_spi_expectation(false, vec![0x21, 40, 40], &mut display, &mut sequence);
+        set_spi_expectation(false, vec![0x22, 2, 2], &mut display, &mut sequence);
+        set_spi_expectation(true, vec![0x10], &mut display, &mut sequence);
+
+        display.swap();
+        display.swap(); // Nothing changed since
+    }
+
+    #[test]
+    fn test_sh1106_partial_swap_starts_at_the_column() {
+        let mut display = get_new_spi_display(OledController::Sh1106);
+        display.fb.mark_clean();
+        display.fb.buffer[30 * 8 + 3] = 0x01;
+        display.fb.buffer[31 * 8 + 4] = 0x02;
+
+        // The columns 30 and 31 are 32 and 33 in the RAM of the SH1106.
+        let mut sequence = Sequence::new();
+        set_spi_expectation(false, vec![0xB3, 0x00, 0x12], &mut display, &mut sequence);
+        set_spi_expectation(true, vec![0x01, 0x00], &mut display, &mut sequence);
+        set_spi_expectation(false, vec![0xB4, 0x00, 0x12], &mut display, &mut sequence);
+        set_spi_expectation(true, vec![0x00, 0x02], &mut display, &mut sequence);
+
+        display.swap();
+    }
+
+    #[test]
+    fn test_alert_and_sleep() {
+        let mut display = get_new_i2c_display(OledController::Sh1106);
+
+        let mut sequence = Sequence::new();
+        for command in [0xA7, 0xA6, 0xAE, 0xAF] {
+            set_i2c_expectation(vec![0x00, command], &mut display, &mut sequence);
+        }
+
+        assert!(display.set_alert(true));
+        display.set_inverted(true);
+        display.sleep();
+        display.wake();
+    }
+}
@@ -1,47 +1,35 @@
-use crate::gui::display_control::{Color, DisplayBuffer, DisplayControl, Point};
+use crate::gui::display_bus::{PinInterface, SpiInterface};
+use crate::gui::display_control::{Brightness, Color, DisplayBuffer, DisplayControl, Point};
 use rppal::gpio::{Gpio, OutputPin};
 use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
 use std::{thread, time};
 
+// The 128x32 panel in portrait, its 128 columns are the height.
+const PANEL_WIDTH: isize = 32;
+const PANEL_HEIGHT: isize = 128;
+
 pub struct RaspberryDisplay<T, U> {
     fb: DisplayBuffer,
     pub spi: T,
     reset_pin: U,
     dc_pin: U,
-}
-
-#[cfg_attr(test, mockall::automock)]
-pub trait SpiInterface {
-    fn send_bytes(&mut self, bytes: &[u8]);
-}
-
-#[cfg_attr(test, mockall::automock)]
-pub trait PinInterface {
-    fn set_pin(&mut self, value: bool);
-}
-
-impl SpiInterface for Spi {
-    fn send_bytes(&mut self, bytes: &[u8]) {
-        self.write(bytes).unwrap();
-    }
-}
-
-impl PinInterface for OutputPin {
-    fn set_pin(&mut self, value: bool) {
-        match value {
-            true => self.set_high(),
-            false => self.set_low(),
-        };
-    }
+    brightness: Brightness,
+    inverted: bool,
+    alert: bool,
+    sleeping: bool,
 }
 
 impl RaspberryDisplay<Spi, OutputPin> {
     pub fn new() -> RaspberryDisplay<Spi, OutputPin> {
         RaspberryDisplay {
-            fb: DisplayBuffer::new(),
+            fb: DisplayBuffer::new(PANEL_WIDTH, PANEL_HEIGHT),
             spi: Spi::new(Bus::Spi0, SlaveSelect::Ss0, 2_000_000, Mode::Mode0).unwrap(),
             reset_pin: Gpio::new().unwrap().get(25).unwrap().into_output(),
             dc_pin: Gpio::new().unwrap().get(24).unwrap().into_output(),
+            brightness: Brightness::Dim,
+            inverted: false,
+            alert: false,
+            sleeping: false,
         }
     }
 }
@@ -49,10 +37,14 @@ impl RaspberryDisplay<Spi, OutputPin> {
 impl<T: SpiInterface, U: PinInterface> RaspberryDisplay<T, U> {
     pub fn new_generic(spi: T, reset_pin: U, dc_pin: U) -> RaspberryDisplay<T, U> {
         RaspberryDisplay {
-            fb: DisplayBuffer::new(),
+            fb: DisplayBuffer::new(PANEL_WIDTH, PANEL_HEIGHT),
             spi,
             reset_pin,
             dc_pin,
+            brightness: Brightness::Dim,
+            inverted: false,
+            alert: false,
+            sleeping: false,
         }
     }
 
@@ -61,6 +53,22 @@ impl<T: SpiInterface, U: PinInterface> RaspberryDisplay<T, U> {
         self.spi.send_bytes(commands);
     }
 
+    // The panel is turned on with its brightness.
+    fn send_display_on(&mut self) {
+        match self.brightness {
+            Brightness::Dim => self.send_display_commands(&[0xAC]), // Display ON in dim mode
+            Brightness::Normal => self.send_display_commands(&[0xAF]), // Display ON normal mode
+        }
+    }
+
+    // An alert inverts the inverted display back to normal.
+    fn send_inversion(&mut self) {
+        match self.inverted != self.alert {
+            true => self.send_display_commands(&[0xA7]), // Set Inverse Display
+            false => self.send_display_commands(&[0xA6]), // Set Normal Display
+        }
+    }
+
     fn reset(&mut self) {
         let interval = time::Duration::from_millis(10);
         self.reset_pin.set_pin(true);
@@ -80,7 +88,9 @@ impl<T: SpiInterface, U: PinInterface> DisplayControl for RaspberryDisplay<T, U>
         self.send_display_commands(&[0x20, 0x01]); // Set Vertical Addressing Mode
         self.send_display_commands(&[0x21, 0x00, 0x7F]); // Set Column Address range to 0-127
         self.send_display_commands(&[0x22, 0x00, 0x03]); // Set Page Address range to 0-3
-        self.send_display_commands(&[0xAC]); // Display ON in dim mode
+        self.send_display_on();
+        self.sleeping = false;
+        self.fb.mark_dirty(); // The reset left the display RAM unknown
         self.swap();
     }
 
@@ -91,9 +101,19 @@ impl<T: SpiInterface, U: PinInterface> DisplayControl for RaspberryDisplay<T, U>
         self.reset_pin.set_pin(false);
     }
 
+    // Only the changed columns and pages are sent, nothing if none changed.
     fn swap(&mut self) {
+        let region = match self.fb.dirty_region() {
+            Some(region) => region,
+            None => return,
+        };
+        let (first_column, last_column) = (region.first_row as u8, region.last_row as u8);
+        let (first_page, last_page) = (region.first_byte as u8, region.last_byte as u8);
+        self.send_display_commands(&[0x21, first_column, last_column]); // Set Column Address range
+        self.send_display_commands(&[0x22, first_page, last_page]); // Set Page Address range
         self.dc_pin.set_pin(true);
-        self.spi.send_bytes(&self.fb.buffer);
+        self.spi.send_bytes(&self.fb.region_data(&region));
+        self.fb.mark_clean();
     }
 
     fn safe_swap(&mut self) {
@@ -103,11 +123,44 @@ impl<T: SpiInterface, U: PinInterface> DisplayControl for RaspberryDisplay<T, U>
     fn fb<'a>(&'a mut self) -> &'a mut DisplayBuffer {
         &mut self.fb
     }
+
+    fn set_contrast(&mut self, contrast: u8) {
+        self.send_display_commands(&[0x81, contrast]); // Set Contrast Control
+    }
+
+    fn set_brightness(&mut self, brightness: Brightness) {
+        self.brightness = brightness;
+        if !self.sleeping {
+            self.send_display_on();
+        }
+    }
+
+    fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+        self.send_inversion();
+    }
+
+    fn set_alert(&mut self, alert: bool) -> bool {
+        self.alert = alert;
+        self.send_inversion();
+        true
+    }
+
+    fn sleep(&mut self) {
+        self.send_display_commands(&[0xAE]); // Display OFF (sleep mode)
+        self.sleeping = true;
+    }
+
+    fn wake(&mut self) {
+        self.send_display_on();
+        self.sleeping = false;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gui::display_bus::{MockPinInterface, MockSpiInterface};
     use mockall::predicate::*;
     use mockall::*;
 
@@ -151,19 +204,51 @@ mod tests {
         display.reset();
     }
 
+    fn set_window_expectation(
+        columns: (u8, u8),
+        pages: (u8, u8),
+        display: &mut RaspberryDisplay<MockSpiInterface, MockPinInterface>,
+        sequence: &mut Sequence,
+    ) {
+        set_pin_expectation(false, &mut display.dc_pin, sequence);
+        set_send_bytes_expectation(vec![0x21, columns.0, columns.1], &mut display.spi, sequence);
+        set_pin_expectation(false, &mut display.dc_pin, sequence);
+        set_send_bytes_expectation(vec![0x22, pages.0, pages.1], &mut display.spi, sequence);
+    }
+
     #[test]
     fn test_swap() {
         let mut display = get_new_mocked_display();
 
         let mut sequence = Sequence::new();
+        set_window_expectation((0, 127), (0, 3), &mut display, &mut sequence);
         set_pin_expectation(true, &mut display.dc_pin, &mut sequence);
         set_send_bytes_expectation(vec![0xAAu8; 512], &mut display.spi, &mut sequence);
+        set_window_expectation((0, 127), (0, 3), &mut display, &mut sequence);
         set_pin_expectation(true, &mut display.dc_pin, &mut sequence);
         set_send_bytes_expectation(vec![0xCCu8; 512], &mut display.spi, &mut sequence);
 
-        display.fb.buffer = [0xAAu8; 512];
+        display.fb.buffer = vec![0xAAu8; 512];
+        display.swap();
+        display.fb.buffer = vec![0xCCu8; 512];
+        display.swap();
+    }
+
+    #[test]
+    fn test_swap_sends_only_the_changed_region() {
+        let mut display = get_new_mocked_display();
+        display.fb.mark_clean();
+
+        let mut sequence = Sequence::new();
+        // Columns 10 to 11 of the pages 1 to 2.
+        set_window_expectation((10, 11), (1, 2), &mut display, &mut sequence);
+        set_pin_expectation(true, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0x01, 0x00, 0x00, 0x80], &mut display.spi, &mut sequence);
+
+        display.swap(); // Nothing changed, nothing is sent
+        display.fb.set_pixel_color(&Point { x: 8, y: 10 }, &Color::White);
+        display.fb.set_pixel_color(&Point { x: 23, y: 11 }, &Color::White);
         display.swap();
-        display.fb.buffer = [0xCCu8; 512];
         display.swap();
     }
 
@@ -210,9 +295,86 @@ mod tests {
         set_send_bytes_expectation(vec![0xAC], &mut display.spi, &mut sequence);
 
         // Send the buffer with all 0u8 to clear the screen to black
+        set_window_expectation((0, 127), (0, 3), &mut display, &mut sequence);
         set_pin_expectation(true, &mut display.dc_pin, &mut sequence);
         set_send_bytes_expectation(vec![0u8; 512], &mut display.spi, &mut sequence);
 
         display.init();
     }
+
+    #[test]
+    fn test_set_contrast() {
+        let mut display = get_new_mocked_display();
+
+        let mut sequence = Sequence::new();
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0x81, 0x20], &mut display.spi, &mut sequence);
+
+        display.set_contrast(0x20);
+    }
+
+    #[test]
+    fn test_sleep_and_wake() {
+        let mut display = get_new_mocked_display();
+
+        let mut sequence = Sequence::new();
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xAE], &mut display.spi, &mut sequence);
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xAC], &mut display.spi, &mut sequence);
+
+        display.sleep();
+        display.wake();
+    }
+
+    #[test]
+    fn test_brightness_applies_on_wake() {
+        let mut display = get_new_mocked_display();
+
+        let mut sequence = Sequence::new();
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xAF], &mut display.spi, &mut sequence);
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xAE], &mut display.spi, &mut sequence);
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xAC], &mut display.spi, &mut sequence);
+
+        display.set_brightness(Brightness::Normal);
+        display.sleep();
+        // A sleeping panel stays off until it wakes up.
+        display.set_brightness(Brightness::Dim);
+        display.wake();
+    }
+
+    #[test]
+    fn test_set_inverted() {
+        let mut display = get_new_mocked_display();
+
+        let mut sequence = Sequence::new();
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xA7], &mut display.spi, &mut sequence);
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xA6], &mut display.spi, &mut sequence);
+
+        display.set_inverted(true);
+        display.set_inverted(false);
+    }
+
+    #[test]
+    fn test_alert_inverts_without_sending_the_frame() {
+        let mut display = get_new_mocked_display();
+
+        let mut sequence = Sequence::new();
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xA7], &mut display.spi, &mut sequence);
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xA6], &mut display.spi, &mut sequence);
+        set_pin_expectation(false, &mut display.dc_pin, &mut sequence);
+        set_send_bytes_expectation(vec![0xA7], &mut display.spi, &mut sequence);
+
+        assert!(display.set_alert(true));
+        // The alert on an inverted display shows it normal.
+        display.set_inverted(true);
+        assert!(display.set_alert(false));
+    }
 }
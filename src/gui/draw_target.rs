@@ -0,0 +1,137 @@
+use std::convert::Infallible;
+
+use embedded_graphics::draw_target::DrawTargetExt;
+use embedded_graphics::image::ImageDrawable;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{
+    DrawTarget, OriginDimensions, Pixel, Point as GraphicsPoint, Size,
+};
+use embedded_graphics::primitives::Rectangle;
+
+use crate::gui::display_control::{Color, DisplayBuffer, Pixmap, Point};
+
+impl From<BinaryColor> for Color {
+    fn from(color: BinaryColor) -> Self {
+        match color {
+            BinaryColor::On => Color::White,
+            BinaryColor::Off => Color::Black,
+        }
+    }
+}
+
+// embedded-graphics counts y from the top, the buffer from the bottom.
+fn to_buffer_point(point: GraphicsPoint, height: isize) -> Point {
+    Point {
+        x: point.x as isize,
+        y: height - 1 - point.y as isize,
+    }
+}
+
+impl OriginDimensions for DisplayBuffer {
+    fn size(&self) -> Size {
+        Size::new(self.width() as u32, self.height() as u32)
+    }
+}
+
+/// Draws embedded-graphics primitives, text and images in the logical width
+/// and height of the buffer, with the origin at the top left as usual there.
+/// Pixels outside of the screen are dropped.
+impl DrawTarget for DisplayBuffer {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let height = self.height();
+        for Pixel(point, color) in pixels {
+            self.set_pixel_color(&to_buffer_point(point, height), &color.into());
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        match color {
+            BinaryColor::On => self.fill_with_white(),
+            BinaryColor::Off => self.fill_with_black(),
+        }
+        Ok(())
+    }
+}
+
+impl<const W: usize, const H: usize> Pixmap<W, H> {
+    /// Every pixel of the pixmap, from the top left.
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel<BinaryColor>> + '_ {
+        self.data.iter().enumerate().flat_map(|(row, data_row)| {
+            data_row.iter().enumerate().map(move |(column, value)| {
+                let point = GraphicsPoint::new(column as i32, row as i32);
+                Pixel(point, BinaryColor::from(*value != 0))
+            })
+        })
+    }
+}
+
+impl<const W: usize, const H: usize> OriginDimensions for Pixmap<W, H> {
+    fn size(&self) -> Size {
+        Size::new(W as u32, H as u32)
+    }
+}
+
+/// Lets a pixmap be drawn as an embedded-graphics Image.
+impl<const W: usize, const H: usize> ImageDrawable for Pixmap<W, H> {
+    type Color = BinaryColor;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        target.draw_iter(self.pixels())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let visible = Rectangle::new(GraphicsPoint::zero(), area.size);
+        self.draw(&mut target.clipped(&visible).translated(-area.top_left))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::image::{Image, ImageDrawableExt};
+    use embedded_graphics::prelude::{Drawable, Primitive};
+    use embedded_graphics::primitives::{Line, PrimitiveStyle};
+
+    #[test]
+    fn test_primitives_are_drawn_from_the_top_left() {
+        let mut display = DisplayBuffer::new(8, 16);
+
+        Line::new(GraphicsPoint::new(0, 0), GraphicsPoint::new(7, 0))
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut display)
+            .unwrap();
+
+        assert!((0..8).all(|x| display.get_pixel_color(&Point { x, y: 15 }) == Ok(Color::White)));
+        assert_eq!(display.buffer.iter().filter(|byte| **byte != 0).count(), 1);
+    }
+
+    #[test]
+    fn test_sub_image_of_a_pixmap() {
+        let mut display = DisplayBuffer::new(8, 16);
+        let pixmap = Pixmap {
+            data: [[1, 0, 1], [0, 1, 0]],
+        };
+        let area = Rectangle::new(GraphicsPoint::new(1, 0), Size::new(2, 2));
+
+        Image::new(&pixmap.sub_image(&area), GraphicsPoint::zero())
+            .draw(&mut display)
+            .unwrap();
+
+        let is_lit = |x, y| display.get_pixel_color(&Point { x, y }) == Ok(Color::White);
+        assert!(!is_lit(0, 15) && is_lit(1, 15) && is_lit(0, 14) && !is_lit(1, 14));
+        assert!(!is_lit(2, 15));
+    }
+}
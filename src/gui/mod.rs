@@ -4,4 +4,20 @@ pub mod display_minifb;
 pub mod display_raspberry;
 
 pub mod block_clock;
+pub mod clock_face;
+pub mod compositor;
+pub mod digit_time;
+pub mod display_bus;
 pub mod display_control;
+pub mod display_max7219;
+pub mod display_model;
+pub mod display_oled;
+pub mod draw_target;
+pub mod night_clock;
+pub mod pie_clock;
+pub mod preset_card;
+pub mod program_banner;
+pub mod render_scheduler;
+pub mod screensaver;
+pub mod shapes;
+pub mod warning_cue;
@@ -0,0 +1,36 @@
+use crate::gui::display_control::{text_width, AsciiText, DisplayBuffer, Point, GLYPH_HEIGHT};
+
+/// Number of heights the clock wanders through, one step per minute.
+const POSITION_COUNT: isize = 10;
+
+/// A small wall clock for quiet hours. It moves a bit every minute, so no
+/// pixel stays lit all night.
+pub fn draw_night_clock(hour: u32, minute: u32, frame_buffer: &mut DisplayBuffer) {
+    frame_buffer.fill_with_black();
+    let time = format!("{:02}:{:02}", hour, minute);
+    let x = (frame_buffer.width() - text_width(&time, 1, 1)) / 2;
+    let step = (frame_buffer.height() - 2 * GLYPH_HEIGHT) / POSITION_COUNT;
+    let y = GLYPH_HEIGHT + (minute as isize % POSITION_COUNT) * step;
+    frame_buffer.write_ascii_text(AsciiText::new(&time, Point { x, y }, 1, false));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_moves_every_minute() {
+        let mut first = DisplayBuffer::new(32, 128);
+        let mut second = DisplayBuffer::new(32, 128);
+
+        draw_night_clock(21, 40, &mut first);
+        draw_night_clock(21, 41, &mut second);
+
+        assert!(first.buffer.iter().any(|byte| *byte != 0));
+        assert!(first
+            .buffer
+            .iter()
+            .zip(second.buffer.iter())
+            .all(|(first, second)| first & second == 0));
+    }
+}
@@ -0,0 +1,127 @@
+use std::f64::consts::PI;
+
+use crate::gui::display_control::{Color, DisplayBuffer, Point};
+
+/// Steps of the sector in a full turn, about one pixel along the edge of a
+/// disc in the 32 pixels of the short side.
+pub const SECTOR_STEPS: u128 = 96;
+/// At most this many ticks, longer durations get a tick every 5, 15 or 60
+/// minutes.
+const MAX_TICKS: u128 = 15;
+const TICK_MINUTES: [u128; 4] = [1, 5, 15, 60];
+// Free pixels between the disc and the ticks.
+const TICK_GAP: isize = 2;
+
+/// The step of the sector for the remaining part of the span, at least one
+/// step while any time is left.
+pub fn sector_step(clock_seconds: u128, span_seconds: u128) -> u128 {
+    let span_seconds = span_seconds.max(1);
+    let clock_seconds = clock_seconds.min(span_seconds);
+    (clock_seconds * SECTOR_STEPS).div_ceil(span_seconds)
+}
+
+// Minutes between two ticks around the edge.
+fn tick_minutes(span_seconds: u128) -> u128 {
+    let span_minutes = span_seconds.div_ceil(60);
+    TICK_MINUTES
+        .iter()
+        .copied()
+        .find(|minutes| span_minutes / minutes <= MAX_TICKS)
+        .unwrap_or(60)
+}
+
+/// Like the Time Timer: a disc shrinking towards twelve o'clock, with the
+/// remaining part of the span counterclockwise from twelve. Ticks mark the
+/// minutes of the span. The disc fills the short side of the screen, on a
+/// landscape screen it sits at the left to leave room for a banner.
+pub fn draw_pie_clock(clock_seconds: u128, span_seconds: u128, frame_buffer: &mut DisplayBuffer) {
+    let (width, height) = (frame_buffer.width(), frame_buffer.height());
+    let tick_radius = (isize::min(width, height) - 1) / 2;
+    let disc_radius = tick_radius - 1 - TICK_GAP;
+    let center = match width > height {
+        true => Point {
+            x: tick_radius,
+            y: height / 2,
+        },
+        false => Point {
+            x: width / 2,
+            y: height / 2,
+        },
+    };
+
+    // The fixed edge at twelve o'clock keeps its column, half a step past it.
+    let step = sector_step(clock_seconds, span_seconds) as f64;
+    let (from, to) = match step > 0.0 {
+        true => (
+            1.0 - step / SECTOR_STEPS as f64,
+            1.0 + 0.5 / SECTOR_STEPS as f64,
+        ),
+        false => (0.0, 0.0),
+    };
+    frame_buffer.fill_pie(&center, disc_radius, from, to, &Color::White);
+
+    let span_minutes = span_seconds.div_ceil(60);
+    let tick_minutes = tick_minutes(span_seconds);
+    for minute in (0..span_minutes).step_by(tick_minutes as usize) {
+        let angle = 2.0 * PI * (1.0 - minute as f64 / span_minutes as f64);
+        for radius in [tick_radius - 1, tick_radius] {
+            let point = Point {
+                x: center.x + (radius as f64 * angle.sin()).round() as isize,
+                y: center.y + (radius as f64 * angle.cos()).round() as isize,
+            };
+            frame_buffer.set_pixel_color(&point, &Color::White);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::display_control::{Orientation, Rotation};
+
+    fn is_lit(display: &DisplayBuffer, x: isize, y: isize) -> bool {
+        display.get_pixel_color(&Point { x, y }) == Ok(Color::White)
+    }
+
+    #[test]
+    fn test_sector_changes_in_steps() {
+        assert_eq!(sector_step(1200, 1200), SECTOR_STEPS);
+        assert_eq!(sector_step(600, 1200), 48);
+        assert_eq!(sector_step(599, 1200), 48);
+        assert_eq!(sector_step(587, 1200), 47);
+        assert_eq!(sector_step(1, 1200), 1);
+        assert_eq!(sector_step(0, 1200), 0);
+        assert_eq!(tick_minutes(20 * 60), 5);
+        assert_eq!(tick_minutes(10 * 60), 1);
+    }
+
+    #[test]
+    fn test_half_the_time_is_the_left_half() {
+        let mut display = DisplayBuffer::new(32, 128);
+
+        draw_pie_clock(600, 1200, &mut display);
+
+        // The disc around (16, 64) has a radius of 12, the ticks of 15.
+        assert!(is_lit(&display, 4, 64) && is_lit(&display, 16, 76));
+        assert!(is_lit(&display, 10, 58) && !is_lit(&display, 22, 70));
+        assert!(!is_lit(&display, 17, 63) && !is_lit(&display, 28, 64));
+        // Four ticks, at 0, 5, 10 and 15 minutes.
+        assert!(is_lit(&display, 16, 79) && is_lit(&display, 16, 78));
+        assert!(is_lit(&display, 1, 64) && is_lit(&display, 31, 64));
+        assert!(is_lit(&display, 16, 49) && !is_lit(&display, 16, 77));
+    }
+
+    #[test]
+    fn test_landscape_puts_the_disc_at_the_left() {
+        let mut display = DisplayBuffer::new(32, 128);
+        display.set_orientation(Orientation {
+            rotation: Rotation::R90,
+            mirrored: false,
+        });
+
+        draw_pie_clock(1200, 1200, &mut display);
+
+        assert!(is_lit(&display, 15, 16) && is_lit(&display, 15, 31));
+        assert!((31..128).all(|x| (0..32).all(|y| !is_lit(&display, x, y))));
+    }
+}
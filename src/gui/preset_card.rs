@@ -0,0 +1,14 @@
+use crate::gui::display_control::{text_width, AsciiText, DisplayBuffer, Point};
+
+/// Name and duration of a preset, shown while choosing one with the button.
+pub fn draw_preset_card(name: &str, duration_ms: u128, frame_buffer: &mut DisplayBuffer) {
+    frame_buffer.fill_with_black();
+    let (width, height) = (frame_buffer.width(), frame_buffer.height());
+    frame_buffer.write_centered_text(name, height * 2 / 3);
+
+    let seconds = duration_ms / 1000;
+    let duration = format!("{:02}:{:02}", seconds / 60, seconds % 60);
+    let x = (width - text_width(&duration, 1, 1)) / 2;
+    let y = height / 4;
+    frame_buffer.write_ascii_text(AsciiText::new(&duration, Point { x, y }, 1, false));
+}
@@ -0,0 +1,57 @@
+use crate::gui::display_control::{
+    text_width, AsciiText, ClipRect, Color, DisplayBuffer, Point, GLYPH_HEIGHT,
+};
+
+const DOT_SIZE: isize = 3;
+const DOT_SPACING: isize = 2;
+
+// The bottom rows of the label and of the dots.
+fn banner_rows(frame_buffer: &DisplayBuffer) -> (isize, isize) {
+    let label_y = frame_buffer.height() - 1 - GLYPH_HEIGHT;
+    (label_y, label_y - 2 - DOT_SIZE)
+}
+
+/// The full width rows at the top the banner covers.
+pub fn program_banner_area(frame_buffer: &DisplayBuffer) -> ClipRect {
+    ClipRect {
+        left: 0,
+        bottom: banner_rows(frame_buffer).1,
+        right: frame_buffer.width() - 1,
+        top: frame_buffer.height() - 1,
+    }
+}
+
+/// The label of the running segment in the top line and one dot per
+/// repetition below it, the dots of the done and current repetitions filled.
+pub fn draw_program_banner(
+    label: &str,
+    repetition: u32,
+    repetitions: u32,
+    frame_buffer: &mut DisplayBuffer,
+) {
+    // Only the first word fits, e.g. "Long" of "Long break".
+    let label = label.split_whitespace().next().unwrap_or("");
+    let width = frame_buffer.width();
+    let (label_y, dots_y) = banner_rows(frame_buffer);
+    let x = isize::max(0, (width - text_width(label, 1, 1)) / 2);
+    frame_buffer.write_ascii_text(AsciiText::new(label, Point { x, y: label_y }, 1, false));
+
+    let repetitions = repetitions as isize;
+    let dots_width = repetitions * DOT_SIZE + (repetitions - 1) * DOT_SPACING;
+    let mut x = isize::max(0, (width - dots_width) / 2);
+    for dot in 1..=repetitions {
+        let fill_color = match dot <= repetition as isize {
+            true => Color::White,
+            false => Color::Black,
+        };
+        frame_buffer.draw_box_with_coords(
+            x,
+            dots_y,
+            x + DOT_SIZE - 1,
+            dots_y + DOT_SIZE - 1,
+            &fill_color,
+            &Color::White,
+        );
+        x += DOT_SIZE + DOT_SPACING;
+    }
+}
@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+/// The loop sleeps until its deadlines, this only catches the ones that
+/// moved unnoticed, e.g. when the system clock is set.
+pub const MAX_SLEEP_MS: u128 = 10_000;
+pub const MAX_FPS_LIMIT: u32 = 60;
+
+/// How smooth the faces are drawn. Fewer frames save power.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderSettings {
+    pub max_fps: u32,
+    /// Tweens the clock when minutes are added or subtracted.
+    pub transitions: bool,
+}
+
+impl RenderSettings {
+    pub fn new() -> Self {
+        RenderSettings {
+            max_fps: 20,
+            transitions: true,
+        }
+    }
+}
+
+/// Wakes the sleeping render loop, e.g. after a web command.
+#[derive(Clone)]
+pub struct RenderWaker {
+    sender: Sender<()>,
+}
+
+impl RenderWaker {
+    pub fn wake(&self) {
+        // The loop is gone when the hourglass quits.
+        let _ = self.sender.send(());
+    }
+}
+
+/// Tells the loop when to draw the next frame and sleeps until then. A
+/// frame is due on a redraw request, but never sooner than the max fps
+/// allow. The loop asks again on every pass, so the requests end with the
+/// sleep.
+pub struct RenderScheduler {
+    sender: Sender<()>,
+    receiver: Receiver<()>,
+    frame_interval_ms: u128,
+    last_frame_ms: Option<u128>,
+    redraw_requested: bool,
+    wake_ms: Option<u128>,
+}
+
+impl RenderScheduler {
+    pub fn new(max_fps: u32) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let mut scheduler = RenderScheduler {
+            sender,
+            receiver,
+            frame_interval_ms: 0,
+            last_frame_ms: None,
+            redraw_requested: false,
+            wake_ms: None,
+        };
+        scheduler.set_max_fps(max_fps);
+        scheduler
+    }
+
+    pub fn waker(&self) -> RenderWaker {
+        RenderWaker {
+            sender: self.sender.clone(),
+        }
+    }
+
+    pub fn set_max_fps(&mut self, max_fps: u32) {
+        self.frame_interval_ms = 1000 / max_fps.clamp(1, MAX_FPS_LIMIT) as u128;
+    }
+
+    /// The next frame as soon as the frame rate allows.
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    /// Wakes the loop at the time, e.g. when the next frame of an animation
+    /// changes. The earliest of several requests counts.
+    pub fn wake_at(&mut self, wake_ms: u128) {
+        self.wake_ms = Some(self.wake_ms.map_or(wake_ms, |ms| ms.min(wake_ms)));
+    }
+
+    // The earliest time the next frame may be drawn.
+    fn earliest_frame_ms(&self) -> u128 {
+        self.last_frame_ms
+            .map_or(0, |last_frame_ms| last_frame_ms + self.frame_interval_ms)
+    }
+
+    // The time the next frame is due, None without a request.
+    fn due_ms(&self) -> Option<u128> {
+        self.redraw_requested.then(|| self.earliest_frame_ms())
+    }
+
+    pub fn is_frame_due(&self, current_time_ms: u128) -> bool {
+        self.due_ms()
+            .is_some_and(|due_ms| due_ms <= current_time_ms)
+    }
+
+    pub fn frame_drawn(&mut self, current_time_ms: u128) {
+        self.last_frame_ms = Some(current_time_ms);
+        self.redraw_requested = false;
+    }
+
+    /// How long the loop may sleep, at most MAX_SLEEP_MS.
+    pub fn sleep_ms(&self, current_time_ms: u128) -> u128 {
+        self.due_ms()
+            .into_iter()
+            .chain(self.wake_ms)
+            .fold(current_time_ms + MAX_SLEEP_MS, Ord::min)
+            .saturating_sub(current_time_ms)
+    }
+
+    /// Sleeps until the next frame is due, the requested wake up or a waker
+    /// wakes the loop. The requests are cleared for the next pass.
+    pub fn wait(&mut self, current_time_ms: u128) {
+        let sleep_ms = self.sleep_ms(current_time_ms);
+        self.redraw_requested = false;
+        self.wake_ms = None;
+        match self
+            .receiver
+            .recv_timeout(Duration::from_millis(sleep_ms as u64))
+        {
+            // Several wakes while busy need only one loop.
+            Ok(()) => while self.receiver.try_recv().is_ok() {},
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {}
+        }
+    }
+}
+
+/// A value moving from one number to another, fast at first and slowing
+/// down towards the end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tween {
+    from: f64,
+    to: f64,
+    start_ms: u128,
+    duration_ms: u128,
+}
+
+impl Tween {
+    pub fn new(from: f64, to: f64, start_ms: u128, duration_ms: u128) -> Self {
+        Tween {
+            from,
+            to,
+            start_ms,
+            duration_ms,
+        }
+    }
+
+    pub fn is_done(&self, current_time_ms: u128) -> bool {
+        current_time_ms >= self.start_ms + self.duration_ms
+    }
+
+    pub fn value(&self, current_time_ms: u128) -> f64 {
+        if self.is_done(current_time_ms) {
+            return self.to;
+        }
+        let progress =
+            current_time_ms.saturating_sub(self.start_ms) as f64 / self.duration_ms as f64;
+        // Ease out cubic
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        self.from + (self.to - self.from) * eased
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_follow_requests_and_the_max_fps() {
+        let mut scheduler = RenderScheduler::new(10);
+        assert!(!scheduler.is_frame_due(1000));
+        assert_eq!(scheduler.sleep_ms(1000), MAX_SLEEP_MS);
+
+        scheduler.request_redraw();
+        assert!(scheduler.is_frame_due(1000));
+        scheduler.frame_drawn(1000);
+
+        // A redraw right after a frame waits for the frame interval.
+        scheduler.request_redraw();
+        assert!(!scheduler.is_frame_due(1050));
+        assert_eq!(scheduler.sleep_ms(1050), 50);
+        assert!(scheduler.is_frame_due(1100));
+        scheduler.frame_drawn(1100);
+
+        // The earliest wake up counts, it doesn't make a frame due.
+        scheduler.wake_at(1900);
+        scheduler.wake_at(1300);
+        assert_eq!(scheduler.sleep_ms(1100), 200);
+        assert!(!scheduler.is_frame_due(1300));
+    }
+
+    #[test]
+    fn test_requests_end_with_the_sleep() {
+        let mut scheduler = RenderScheduler::new(20);
+        scheduler.request_redraw();
+        scheduler.wake_at(100);
+        scheduler.waker().wake();
+        scheduler.wait(0);
+
+        // Requests nobody drew or asked for again must not keep the loop busy.
+        assert!(!scheduler.is_frame_due(1000));
+        assert_eq!(scheduler.sleep_ms(1000), MAX_SLEEP_MS);
+    }
+
+    #[test]
+    fn test_waker_ends_the_sleep() {
+        let mut scheduler = RenderScheduler::new(20);
+        let waker = scheduler.waker();
+        waker.wake();
+        waker.wake();
+
+        scheduler.wake_at(60_000);
+        let start = std::time::Instant::now();
+        scheduler.wait(0);
+        assert!(start.elapsed() < Duration::from_millis(50));
+        assert!(scheduler.receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_tween_eases_out() {
+        let tween = Tween::new(-60.0, 0.0, 1000, 500);
+        assert_eq!(tween.value(900), -60.0);
+        assert_eq!(tween.value(1250), -7.5);
+        assert!(!tween.is_done(1499));
+        assert_eq!(tween.value(1500), 0.0);
+        assert!(tween.is_done(1500));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::gui::display_control::{Color, DisplayBuffer};
+
+const GRAIN_SIZE: isize = 3;
+
+// Runs from 0 up to max and back down, one step per frame.
+fn bounce(frame: u128, max: isize) -> isize {
+    let period = 2 * max as u128;
+    let phase = (frame % period) as isize;
+    if phase <= max {
+        phase
+    } else {
+        2 * max - phase
+    }
+}
+
+/// A grain of sand bouncing around the dark screen, so every pixel is only
+/// lit for a moment.
+pub fn draw_screensaver(frame: u128, frame_buffer: &mut DisplayBuffer) {
+    frame_buffer.fill_with_black();
+    let x = bounce(frame, frame_buffer.width() - GRAIN_SIZE);
+    let y = bounce(frame, frame_buffer.height() - GRAIN_SIZE);
+    frame_buffer.draw_box_with_coords(
+        x,
+        y,
+        x + GRAIN_SIZE - 1,
+        y + GRAIN_SIZE - 1,
+        &Color::White,
+        &Color::White,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::display_control::Point;
+
+    #[test]
+    fn test_grain_bounces_off_the_edges() {
+        let mut display = DisplayBuffer::new(32, 128);
+
+        draw_screensaver(32, &mut display);
+
+        // The grain reached the right edge at frame 29 and moves back, while
+        // it still rises towards the top.
+        assert!(display.get_pixel_color(&Point { x: 26, y: 32 }) == Ok(Color::White));
+        assert!(display.get_pixel_color(&Point { x: 25, y: 32 }) == Ok(Color::Black));
+        assert_eq!(
+            display
+                .buffer
+                .iter()
+                .map(|byte| byte.count_ones())
+                .sum::<u32>(),
+            (GRAIN_SIZE * GRAIN_SIZE) as u32
+        );
+    }
+}
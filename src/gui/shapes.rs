@@ -0,0 +1,405 @@
+use std::f64::consts::PI;
+
+use crate::gui::display_control::{Color, DisplayBuffer, Point};
+
+// Whether the offset from the center lies in the sector between the
+// fractions of a turn, clockwise from twelve o'clock.
+fn is_in_sector(dx: isize, dy: isize, from: f64, to: f64) -> bool {
+    let sweep = to - from;
+    if sweep >= 1.0 {
+        return true;
+    }
+    if sweep <= 0.0 {
+        return false;
+    }
+    if dx == 0 && dy == 0 {
+        return true;
+    }
+    let angle = f64::atan2(dx as f64, dy as f64) / (2.0 * PI);
+    (angle - from).rem_euclid(1.0) < sweep
+}
+
+/// Shapes for the faces, clipped to the screen like all drawing. Sectors
+/// are given by fractions of a full turn, clockwise from twelve o'clock.
+impl DisplayBuffer {
+    // Calls the plot for the points of the circle outline in one octant,
+    // the plot mirrors them to the other seven.
+    fn for_circle_octant(radius: isize, mut plot: impl FnMut(isize, isize)) {
+        let mut x = radius;
+        let mut y = 0;
+        let mut err = 1 - radius;
+        while x >= y {
+            plot(x, y);
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    // The offsets of the outline, each once.
+    fn circle_outline(radius: isize) -> Vec<(isize, isize)> {
+        let mut offsets = Vec::new();
+        DisplayBuffer::for_circle_octant(radius, |x, y| {
+            for (dx, dy) in [(x, y), (y, x), (-y, x), (-x, y)] {
+                offsets.push((dx, dy));
+                offsets.push((-dx, -dy));
+            }
+        });
+        offsets.sort_unstable();
+        offsets.dedup();
+        offsets
+    }
+
+    // The offsets of the filled disc, row by row. It covers the outline.
+    fn disc_rows(radius: isize) -> Vec<(isize, isize)> {
+        let mut half_widths = vec![0; radius.max(0) as usize + 1];
+        DisplayBuffer::for_circle_octant(radius, |x, y| {
+            half_widths[y as usize] = half_widths[y as usize].max(x);
+            half_widths[x as usize] = half_widths[x as usize].max(y);
+        });
+        let mut rows = Vec::new();
+        for (dy, half_width) in half_widths.iter().enumerate() {
+            rows.push((dy as isize, *half_width));
+            if dy > 0 {
+                rows.push((-(dy as isize), *half_width));
+            }
+        }
+        rows
+    }
+
+    pub fn draw_circle(&mut self, center: &Point, radius: isize, color: &Color) {
+        self.draw_arc(center, radius, 0.0, 1.0, color);
+    }
+
+    pub fn fill_circle(&mut self, center: &Point, radius: isize, color: &Color) {
+        self.fill_pie(center, radius, 0.0, 1.0, color);
+    }
+
+    /// The part of the circle outline from the fraction to the fraction.
+    pub fn draw_arc(&mut self, center: &Point, radius: isize, from: f64, to: f64, color: &Color) {
+        if radius < 0 {
+            return;
+        }
+        for (dx, dy) in DisplayBuffer::circle_outline(radius) {
+            if is_in_sector(dx, dy, from, to) {
+                let point = Point {
+                    x: center.x + dx,
+                    y: center.y + dy,
+                };
+                self.set_pixel_color(&point, color);
+            }
+        }
+    }
+
+    /// The slice of the filled circle from the fraction to the fraction, e.g.
+    /// 0.0 to 0.25 is the quarter from twelve to three o'clock.
+    pub fn fill_pie(&mut self, center: &Point, radius: isize, from: f64, to: f64, color: &Color) {
+        if radius < 0 {
+            return;
+        }
+        for (dy, half_width) in DisplayBuffer::disc_rows(radius) {
+            for dx in -half_width..=half_width {
+                if is_in_sector(dx, dy, from, to) {
+                    let point = Point {
+                        x: center.x + dx,
+                        y: center.y + dy,
+                    };
+                    self.set_pixel_color(&point, color);
+                }
+            }
+        }
+    }
+
+    /// Fills the polygon with its outline. Edges may cross, overlapping parts
+    /// alternate between inside and outside.
+    pub fn fill_polygon(&mut self, corners: &[Point], color: &Color) {
+        if corners.is_empty() {
+            return;
+        }
+        let bottom = corners.iter().map(|corner| corner.y).min().unwrap();
+        let top = corners.iter().map(|corner| corner.y).max().unwrap();
+        let edges: Vec<(&Point, &Point)> =
+            corners.iter().zip(corners.iter().cycle().skip(1)).collect();
+        for y in bottom..=top {
+            // Each edge counts from its lower end up to below its upper end,
+            // so a corner between two edges is only crossed once.
+            let mut crossings: Vec<f64> = edges
+                .iter()
+                .filter(|(start, end)| (start.y <= y) != (end.y <= y))
+                .map(|(start, end)| {
+                    let t = (y - start.y) as f64 / (end.y - start.y) as f64;
+                    start.x as f64 + t * (end.x - start.x) as f64
+                })
+                .collect();
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for span in crossings.chunks_exact(2) {
+                let left = span[0].ceil() as isize;
+                let right = span[1].floor() as isize;
+                for x in left..=right {
+                    self.set_pixel_color(&Point { x, y }, color);
+                }
+            }
+        }
+        for (start, end) in edges {
+            self.draw_line_with_points(start, end, color);
+        }
+    }
+
+    pub fn fill_triangle(&mut self, a: &Point, b: &Point, c: &Point, color: &Color) {
+        self.fill_polygon(&[a.clone(), b.clone(), c.clone()], color);
+    }
+
+    /// Paints the area of same colored pixels around the start, up to the
+    /// pixels of the other color. Diagonal neighbours don't belong to it,
+    /// the area ends at the clip.
+    pub fn flood_fill(&mut self, start: &Point, color: &Color) {
+        let clip = self.clip();
+        let area_color = match self.get_pixel_color(start) {
+            Ok(area_color) if area_color != *color => area_color,
+            _ => return,
+        };
+        let mut pending = vec![start.clone()];
+        while let Some(point) = pending.pop() {
+            // Writes outside of the clip are dropped, the pixel would stay.
+            if clip.is_some_and(|clip| !clip.contains(&point))
+                || self.get_pixel_color(&point) != Ok(area_color)
+            {
+                continue;
+            }
+            self.set_pixel_color(&point, color);
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                pending.push(Point {
+                    x: point.x + dx,
+                    y: point.y + dy,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::display_control::ClipRect;
+
+    // The screen from the top row down, lit pixels are '#'.
+    fn rows(display: &DisplayBuffer) -> Vec<String> {
+        (0..display.height())
+            .rev()
+            .map(|y| {
+                (0..display.width())
+                    .map(|x| match display.get_pixel_color(&Point { x, y }) {
+                        Ok(Color::White) => '#',
+                        _ => '.',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_circle() {
+        let mut display = DisplayBuffer::new(8, 8);
+
+        display.draw_circle(&Point { x: 3, y: 3 }, 3, &Color::White);
+
+        assert_eq!(
+            rows(&display),
+            vec![
+                "........", //
+                "..###...", //
+                ".#...#..", //
+                "#.....#.", //
+                "#.....#.", //
+                "#.....#.", //
+                ".#...#..", //
+                "..###...", //
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_circle_covers_the_outline() {
+        let mut outline = DisplayBuffer::new(16, 16);
+        let mut filled = DisplayBuffer::new(16, 16);
+        let center = Point { x: 7, y: 7 };
+
+        outline.draw_circle(&center, 6, &Color::White);
+        filled.fill_circle(&center, 6, &Color::White);
+
+        assert!(outline
+            .buffer
+            .iter()
+            .zip(filled.buffer.iter())
+            .all(|(outline, filled)| outline & !filled == 0));
+        assert_eq!(
+            rows(&filled)[8..].to_vec(),
+            vec![
+                ".#############..", //
+                ".#############..", //
+                ".#############..", //
+                "..###########...", //
+                "...#########....", //
+                "....#######.....", //
+                ".....#####......", //
+                "................", //
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_pie_quarters() {
+        let mut display = DisplayBuffer::new(8, 8);
+
+        // From twelve to three o'clock, including the center.
+        display.fill_pie(&Point { x: 3, y: 3 }, 3, 0.0, 0.25, &Color::White);
+
+        assert_eq!(
+            rows(&display),
+            vec![
+                "........", //
+                "...##...", //
+                "...###..", //
+                "...####.", //
+                "...#....", //
+                "........", //
+                "........", //
+                "........", //
+            ]
+        );
+    }
+
+    #[test]
+    fn test_draw_arc_wraps_around_twelve() {
+        let mut display = DisplayBuffer::new(8, 8);
+
+        // From nine o'clock, included, to three o'clock, excluded.
+        display.draw_arc(&Point { x: 3, y: 3 }, 3, 0.75, 1.25, &Color::White);
+
+        assert_eq!(
+            rows(&display),
+            vec![
+                "........", //
+                "..###...", //
+                ".#...#..", //
+                "#.....#.", //
+                "#.......", //
+                "........", //
+                "........", //
+                "........", //
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_triangle() {
+        let mut display = DisplayBuffer::new(8, 8);
+
+        display.fill_triangle(
+            &Point { x: 0, y: 0 },
+            &Point { x: 6, y: 0 },
+            &Point { x: 0, y: 6 },
+            &Color::White,
+        );
+
+        assert_eq!(
+            rows(&display),
+            vec![
+                "........", //
+                "#.......", //
+                "##......", //
+                "###.....", //
+                "####....", //
+                "#####...", //
+                "######..", //
+                "#######.", //
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_polygon_is_clipped() {
+        let mut display = DisplayBuffer::new(8, 8);
+
+        // A diamond reaching over the left edge.
+        display.fill_polygon(
+            &[
+                Point { x: 1, y: 0 },
+                Point { x: 4, y: 3 },
+                Point { x: 1, y: 6 },
+                Point { x: -2, y: 3 },
+            ],
+            &Color::White,
+        );
+
+        assert_eq!(
+            rows(&display),
+            vec![
+                "........", //
+                ".#......", //
+                "###.....", //
+                "####....", //
+                "#####...", //
+                "####....", //
+                "###.....", //
+                ".#......", //
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flood_fill_stops_at_the_outline() {
+        let mut display = DisplayBuffer::new(8, 8);
+        display.draw_box_with_coords(1, 1, 5, 5, &Color::Black, &Color::White);
+        // A gap in the corner only connects diagonally.
+        display.set_pixel_color(&Point { x: 5, y: 5 }, &Color::Black);
+
+        display.flood_fill(&Point { x: 3, y: 3 }, &Color::White);
+
+        assert_eq!(
+            rows(&display),
+            vec![
+                "........", //
+                "........", //
+                ".####...", //
+                ".#####..", //
+                ".#####..", //
+                ".#####..", //
+                ".#####..", //
+                "........", //
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flood_fill_ends_at_the_clip() {
+        let mut display = DisplayBuffer::new(8, 8);
+        display.push_clip(ClipRect {
+            left: 0,
+            bottom: 0,
+            right: 3,
+            top: 3,
+        });
+
+        display.flood_fill(&Point { x: 1, y: 1 }, &Color::White);
+        display.pop_clip();
+
+        assert_eq!(
+            rows(&display),
+            vec![
+                "........", //
+                "........", //
+                "........", //
+                "........", //
+                "####....", //
+                "####....", //
+                "####....", //
+                "####....", //
+            ]
+        );
+    }
+}
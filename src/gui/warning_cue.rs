@@ -0,0 +1,21 @@
+use crate::gui::display_control::{DisplayBuffer, Point};
+
+pub const WARNING_CUE_MS: u128 = 3000;
+pub const BLINK_INTERVAL_MS: u128 = 250;
+
+/// Blinks an inverted frame around whatever is drawn, so the clock face
+/// stays readable while the warning is shown.
+pub fn draw_warning_cue(elapsed_ms: u128, frame_buffer: &mut DisplayBuffer) {
+    if elapsed_ms >= WARNING_CUE_MS || !(elapsed_ms / BLINK_INTERVAL_MS).is_multiple_of(2) {
+        return;
+    }
+    let (width, height) = (frame_buffer.width(), frame_buffer.height());
+    for x in 0..width {
+        frame_buffer.toggle_pixel(&Point { x, y: 0 });
+        frame_buffer.toggle_pixel(&Point { x, y: height - 1 });
+    }
+    for y in 1..(height - 1) {
+        frame_buffer.toggle_pixel(&Point { x: 0, y });
+        frame_buffer.toggle_pixel(&Point { x: width - 1, y });
+    }
+}
@@ -1,13 +1,226 @@
 use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::alarm::AlarmProfile;
+use crate::ambient::{AmbientSchedule, DisplaySettings};
+use crate::audio::sound_bank::SoundId;
+use crate::config::{default_presets, Preset};
+use crate::gui::clock_face::ClockFace;
+use crate::gui::display_control::Orientation;
+use crate::gui::display_model::DisplayModel;
+use crate::gui::render_scheduler::RenderSettings;
+use crate::idle::{IdleSettings, IdleStage};
+use crate::program::{Program, ProgramRun};
+use crate::quiet_hours::QuietHours;
+use crate::schedule::{take_due_presets, ScheduleRule};
 
 pub const MAXIMUM_DURATION_MS: u128 = 52 * 60 * 1000;
+pub const MAXIMUM_TICK_INTERVAL_S: u32 = 60;
+pub const SNOOZE_DURATION_MS: u128 = 5 * 60 * 1000;
+const STOPWATCH_FACE_WRAP_S: u128 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    Countdown,
+    Stopwatch,
+}
+
+/// Changes made from outside the main loop, e.g. by the webservice.
+/// The main loop takes them to react with sounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HourglassEvent {
+    Started,
+    Stopped,
+    Paused,
+    Resumed,
+    MinuteAdded,
+    MinuteSubtracted,
+    AlarmAcknowledged,
+    Snoozed,
+    /// The next segment of a program started, with its transition sound.
+    SegmentStarted(Option<SoundId>),
+    Lap,
+}
+
+/// How the last alarm ended. Without acknowledgement it ran for the
+/// duration of its profile or until the timer was stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlarmRecord {
+    pub expired_at_ms: u128,
+    pub acknowledged_at_ms: Option<u128>,
+    pub snoozed: bool,
+}
+
+/// Optional tick/tock sound played while the timer runs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TickSettings {
+    pub enabled: bool,
+    pub interval_s: u32,
+    pub volume: f32,
+}
+
+impl TickSettings {
+    pub fn new() -> Self {
+        TickSettings {
+            enabled: false,
+            interval_s: 1,
+            volume: 0.3,
+        }
+    }
+
+    /// The settings within the limits of the web interface, e.g. for a
+    /// hand edited config. A zero interval would never tick.
+    pub fn sanitized(self) -> Self {
+        TickSettings {
+            interval_s: self.interval_s.clamp(1, MAXIMUM_TICK_INTERVAL_S),
+            volume: self.volume.clamp(0f32, 1f32),
+            ..self
+        }
+    }
+}
+
+/// Signal given when the remaining time falls below remaining_s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    pub remaining_s: u32,
+    pub sound: SoundId,
+}
+
+pub fn default_warnings() -> Vec<Warning> {
+    vec![
+        Warning {
+            remaining_s: 5 * 60,
+            sound: SoundId::Warning,
+        },
+        Warning {
+            remaining_s: 60,
+            sound: SoundId::OneMinuteWarning,
+        },
+    ]
+}
+
+/// Parses warnings like "300:warning,60:one_minute_warning". The sound is
+/// optional and defaults to the warning sound, "none" means no warnings.
+pub fn parse_warnings(text: &str) -> Result<Vec<Warning>, String> {
+    if text == "none" {
+        return Ok(Vec::new());
+    }
+    text.split(',')
+        .map(|item| {
+            let (remaining_s, sound) = match item.split_once(':') {
+                Some((remaining_s, sound_name)) => (
+                    remaining_s,
+                    SoundId::from_name(sound_name)
+                        .ok_or(format!("Unknown sound \"{}\".", sound_name))?,
+                ),
+                None => (item, SoundId::Warning),
+            };
+            let remaining_s = remaining_s
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid remaining seconds \"{}\".", remaining_s))?;
+            Ok(Warning { remaining_s, sound })
+        })
+        .collect()
+}
+
+pub fn format_warnings(warnings: &[Warning]) -> String {
+    if warnings.is_empty() {
+        return "none".to_string();
+    }
+    warnings
+        .iter()
+        .map(|warning| format!("{}:{}", warning.remaining_s, warning.sound.name()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Finds the warnings whose threshold was crossed by time passing.
+///
+/// Both checked points in time are evaluated against the current target
+/// time, so adding or subtracting minutes never triggers a warning by
+/// itself. A warning fires again only if the countdown crosses it again.
+pub struct WarningTracker {
+    last_check_ms: Option<u128>,
+}
+
+impl WarningTracker {
+    pub fn new() -> Self {
+        WarningTracker {
+            last_check_ms: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.last_check_ms = None;
+    }
+
+    pub fn check(&mut self, state: &HourglassState, current_time_ms: u128) -> Vec<Warning> {
+        let last_check_ms = self.last_check_ms.replace(current_time_ms);
+        let last_check_ms = match last_check_ms {
+            Some(last_check_ms) if state.ticking && state.mode == TimerMode::Countdown => {
+                last_check_ms
+            }
+            _ => return Vec::new(),
+        };
+
+        // A freshly started timer has never been above its duration.
+        let last_remaining_ms = state.remaining_ms(last_check_ms).min(state.duration_ms);
+        let remaining_ms = state.remaining_ms(current_time_ms);
+        state
+            .warnings
+            .iter()
+            .copied()
+            .filter(|warning| {
+                let threshold_ms = warning.remaining_s as u128 * 1000;
+                last_remaining_ms > threshold_ms && remaining_ms <= threshold_ms
+            })
+            .collect()
+    }
+}
 
 #[derive(Debug)]
 pub struct HourglassState {
     pub ticking: bool,
     pub finalize: bool,
+    pub mode: TimerMode,
+    /// End of the countdown or start of the stopwatch, both are moved by
+    /// the time spent paused.
     pub target_time_ms: u128,
     pub duration_ms: u128,
+    pub paused_at_ms: Option<u128>,
+    /// Elapsed time of the stopwatch at each lap.
+    pub laps: Vec<u128>,
+    pub tick_settings: TickSettings,
+    pub warnings: Vec<Warning>,
+    pub alarm_profiles: Vec<AlarmProfile>,
+    pub alarm_profile: AlarmProfile,
+    pub clock_face: ClockFace,
+    pub presets: Vec<Preset>,
+    pub programs: Vec<Program>,
+    pub program: Option<ProgramRun>,
+    pub schedules: Vec<ScheduleRule>,
+    pub quiet_hours: QuietHours,
+    pub idle: IdleSettings,
+    pub display: DisplaySettings,
+    pub orientation: Orientation,
+    /// Only read at start up, a change needs a restart.
+    pub display_model: DisplayModel,
+    pub ambient: AmbientSchedule,
+    pub render: RenderSettings,
+    /// Time of the last input, web command or tick of a timer.
+    pub last_activity_ms: u128,
+    pub last_alarm: Option<AlarmRecord>,
+    pub events: Vec<HourglassEvent>,
+}
+
+pub fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
 }
 
 impl HourglassState {
@@ -15,10 +228,845 @@ impl HourglassState {
         HourglassState {
             ticking: false,
             finalize: false,
+            mode: TimerMode::Countdown,
             target_time_ms: 0,
             duration_ms: 0,
+            paused_at_ms: None,
+            laps: Vec::new(),
+            tick_settings: TickSettings::new(),
+            warnings: default_warnings(),
+            alarm_profiles: AlarmProfile::built_in(),
+            alarm_profile: AlarmProfile::default_profile(),
+            clock_face: ClockFace::Blocks,
+            presets: default_presets(),
+            programs: vec![Program::pomodoro()],
+            program: None,
+            schedules: Vec::new(),
+            quiet_hours: QuietHours::new(),
+            idle: IdleSettings::new(),
+            display: DisplaySettings::new(),
+            orientation: Orientation::new(),
+            display_model: DisplayModel::new(),
+            ambient: AmbientSchedule::new(),
+            render: RenderSettings::new(),
+            last_activity_ms: 0,
+            last_alarm: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Wakes the idle screen.
+    pub fn touch(&mut self, current_time_ms: u128) {
+        self.last_activity_ms = current_time_ms;
+    }
+
+    pub fn idle_stage(&self, current_time_ms: u128) -> IdleStage {
+        self.idle
+            .stage_at(current_time_ms.saturating_sub(self.last_activity_ms))
+    }
+
+    pub fn take_events(&mut self) -> Vec<HourglassEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at_ms.is_some()
+    }
+
+    /// True while the timer counts, i.e. it is ticking and not paused.
+    pub fn is_running(&self) -> bool {
+        self.ticking && !self.is_paused()
+    }
+
+    // The clock stands still at the time of the pause.
+    fn reference_time_ms(&self, current_time_ms: u128) -> u128 {
+        self.paused_at_ms.unwrap_or(current_time_ms)
+    }
+
+    /// Remaining time of the countdown, frozen while paused.
+    pub fn remaining_ms(&self, current_time_ms: u128) -> u128 {
+        match self.mode {
+            TimerMode::Countdown => self
+                .target_time_ms
+                .saturating_sub(self.reference_time_ms(current_time_ms)),
+            TimerMode::Stopwatch => 0,
+        }
+    }
+
+    /// Time counted so far, frozen while paused.
+    pub fn elapsed_ms(&self, current_time_ms: u128) -> u128 {
+        match self.mode {
+            TimerMode::Countdown => self
+                .duration_ms
+                .saturating_sub(self.remaining_ms(current_time_ms)),
+            TimerMode::Stopwatch => self
+                .reference_time_ms(current_time_ms)
+                .saturating_sub(self.target_time_ms),
+        }
+    }
+
+    /// The seconds shown by the clock face: remaining for the countdown,
+    /// elapsed within the current hour for the stopwatch.
+    pub fn clock_seconds(&self, current_time_ms: u128) -> u128 {
+        match self.mode {
+            TimerMode::Countdown => self.remaining_ms(current_time_ms) / 1000,
+            TimerMode::Stopwatch => self.elapsed_ms(current_time_ms) / 1000 % STOPWATCH_FACE_WRAP_S,
+        }
+    }
+
+    /// The target time and the settings of the wanted tick sound, None while
+    /// the countdown doesn't run or during quiet hours.
+    pub fn wanted_ticking(
+        &self,
+        current_time_ms: u128,
+        is_quiet: bool,
+    ) -> Option<(u128, TickSettings)> {
+        let is_wanted = self.tick_settings.enabled
+            && self.is_running()
+            && self.mode == TimerMode::Countdown
+            && self.remaining_ms(current_time_ms) > 0
+            && !is_quiet;
+        is_wanted.then_some((self.target_time_ms, self.tick_settings))
+    }
+
+    /// When the clock seconds change next, None while the clock stands.
+    pub fn next_clock_change_ms(&self, current_time_ms: u128) -> Option<u128> {
+        if !self.is_running() {
+            return None;
+        }
+        let wait_ms = match self.mode {
+            TimerMode::Countdown => self.remaining_ms(current_time_ms) % 1000 + 1,
+            TimerMode::Stopwatch => 1000 - self.elapsed_ms(current_time_ms) % 1000,
+        };
+        Some(current_time_ms + wait_ms)
+    }
+
+    /// When the running countdown crosses the next warning, None without
+    /// warnings ahead.
+    pub fn next_warning_ms(&self, current_time_ms: u128) -> Option<u128> {
+        if !self.is_running() || self.mode != TimerMode::Countdown {
+            return None;
+        }
+        let remaining_ms = self.remaining_ms(current_time_ms);
+        self.warnings
+            .iter()
+            .map(|warning| warning.remaining_s as u128 * 1000)
+            .filter(|threshold_ms| *threshold_ms < remaining_ms)
+            .map(|threshold_ms| self.target_time_ms - threshold_ms)
+            .min()
+    }
+
+    /// The seconds the clock face runs through: the duration of the
+    /// countdown, the hour of the stopwatch.
+    pub fn clock_span_seconds(&self) -> u128 {
+        match self.mode {
+            TimerMode::Countdown => self.duration_ms / 1000,
+            TimerMode::Stopwatch => STOPWATCH_FACE_WRAP_S,
+        }
+    }
+
+    /// True once a countdown reached zero, the stopwatch never ends.
+    pub fn is_time_up(&self, current_time_ms: u128) -> bool {
+        self.ticking
+            && self.mode == TimerMode::Countdown
+            && self.remaining_ms(current_time_ms) == 0
+    }
+
+    pub fn pause(&mut self, current_time_ms: u128) {
+        if self.is_running() {
+            self.paused_at_ms = Some(current_time_ms);
+            self.events.push(HourglassEvent::Paused);
+        }
+    }
+
+    pub fn resume(&mut self, current_time_ms: u128) {
+        if let Some(paused_at_ms) = self.paused_at_ms.take() {
+            self.target_time_ms += current_time_ms.saturating_sub(paused_at_ms);
+            self.events.push(HourglassEvent::Resumed);
+        }
+    }
+
+    /// Starts the countdown of duration_ms without pushing an event.
+    pub fn start(&mut self, current_time_ms: u128) {
+        self.mode = TimerMode::Countdown;
+        self.target_time_ms = current_time_ms + self.duration_ms;
+        self.ticking = true;
+        self.paused_at_ms = None;
+    }
+
+    /// Adds a minute, an idle timer starts with it. A running or paused
+    /// countdown gets the minute on top of its remaining time, a ringing
+    /// alarm a minute from now. False for the stopwatch, it has no duration.
+    pub fn add_minute(&mut self, current_time_ms: u128) -> bool {
+        if self.mode == TimerMode::Stopwatch {
+            return false;
+        }
+        let duration_ms = self
+            .duration_ms
+            .saturating_add(60000)
+            .min(MAXIMUM_DURATION_MS);
+        let added_ms = duration_ms.saturating_sub(self.duration_ms);
+        self.duration_ms = duration_ms;
+        if self.ticking {
+            let reference_time_ms = self.reference_time_ms(current_time_ms);
+            self.target_time_ms = self.target_time_ms.max(reference_time_ms) + added_ms;
+            self.events.push(HourglassEvent::MinuteAdded);
+        } else {
+            self.start(current_time_ms);
+            self.events.push(HourglassEvent::Started);
+        }
+        true
+    }
+
+    /// Takes a minute off the duration and the remaining time. False for
+    /// the stopwatch.
+    pub fn subtract_minute(&mut self) -> bool {
+        if self.mode == TimerMode::Stopwatch {
+            return false;
+        }
+        let duration_ms = self.duration_ms.saturating_sub(60000);
+        if self.ticking {
+            let subtracted_ms = self.duration_ms - duration_ms;
+            self.target_time_ms -= subtracted_ms;
+        }
+        self.duration_ms = duration_ms;
+        self.events.push(HourglassEvent::MinuteSubtracted);
+        true
+    }
+
+    /// Starts the preset with its face and alarm profile, if it has them.
+    pub fn start_preset(&mut self, name: &str, current_time_ms: u128) -> bool {
+        let preset = match self.presets.iter().find(|preset| preset.name == name) {
+            Some(preset) => preset.clone(),
+            None => return false,
+        };
+        self.program = None;
+        self.duration_ms = preset.duration_ms.clamp(0, MAXIMUM_DURATION_MS);
+        if let Some(clock_face) = preset.clock_face {
+            self.clock_face = clock_face;
+        }
+        if let Some(alarm_profile) = &preset.alarm_profile {
+            self.select_alarm_profile(alarm_profile);
+        }
+        self.start(current_time_ms);
+        self.events.push(HourglassEvent::Started);
+        true
+    }
+
+    /// Starts the preset of a due schedule rule unless a timer is already
+    /// running, then the occurrence is dropped. True if any rule fired.
+    pub fn run_schedules(&mut self, local_time: NaiveDateTime, current_time_ms: u128) -> bool {
+        let due_presets = take_due_presets(&mut self.schedules, local_time);
+        if !self.ticking {
+            if let Some(preset) = due_presets.first() {
+                self.start_preset(preset, current_time_ms);
+            }
+        }
+        !due_presets.is_empty()
+    }
+
+    pub fn start_program(&mut self, name: &str, current_time_ms: u128) -> bool {
+        let program = match self.programs.iter().find(|program| program.name == name) {
+            Some(program) if program.step_count() > 0 => program.clone(),
+            _ => return false,
+        };
+        self.program = Some(ProgramRun { program, step: 0 });
+        self.mode = TimerMode::Countdown;
+        self.target_time_ms = current_time_ms;
+        self.ticking = true;
+        self.paused_at_ms = None;
+        self.enter_program_step();
+        self.events.push(HourglassEvent::Started);
+        true
+    }
+
+    // Continues the countdown seamlessly with the current step of the program.
+    fn enter_program_step(&mut self) {
+        let step = match self.program.as_ref().and_then(|run| run.current()) {
+            Some(step) => step,
+            None => return,
+        };
+        let duration_ms = step.segment.duration_ms.clamp(0, MAXIMUM_DURATION_MS);
+        if let Some(clock_face) = step.segment.clock_face {
+            self.clock_face = clock_face;
+        }
+        self.duration_ms = duration_ms;
+        self.target_time_ms += duration_ms;
+    }
+
+    /// Moves on to the next segments of a program whose time is up. The
+    /// last segment is left as it is, so its end sounds the alarm.
+    pub fn advance_program(&mut self, current_time_ms: u128) {
+        while self.is_running() && self.is_time_up(current_time_ms) {
+            let run = match self.program.as_mut() {
+                Some(run) if run.step + 1 < run.program.step_count() => run,
+                _ => return,
+            };
+            run.step += 1;
+            let transition_sound = run.current().and_then(|step| step.segment.transition_sound);
+            self.enter_program_step();
+            self.events.push(HourglassEvent::SegmentStarted(transition_sound));
+        }
+    }
+
+    pub fn start_stopwatch(&mut self, current_time_ms: u128) {
+        self.program = None;
+        self.mode = TimerMode::Stopwatch;
+        self.target_time_ms = current_time_ms;
+        self.duration_ms = 0;
+        self.ticking = true;
+        self.paused_at_ms = None;
+        self.laps.clear();
+        self.events.push(HourglassEvent::Started);
+    }
+
+    /// Records the elapsed time of the stopwatch, also while paused.
+    pub fn lap(&mut self, current_time_ms: u128) -> Option<u128> {
+        if !self.ticking || self.mode != TimerMode::Stopwatch {
+            return None;
+        }
+        let elapsed_ms = self.elapsed_ms(current_time_ms);
+        self.laps.push(elapsed_ms);
+        self.events.push(HourglassEvent::Lap);
+        Some(elapsed_ms)
+    }
+
+    /// Resets the timer to the idle state without pushing an event.
+    pub fn stop(&mut self, current_time_ms: u128) {
+        self.program = None;
+        self.mode = TimerMode::Countdown;
+        self.target_time_ms = current_time_ms;
+        self.duration_ms = 0;
+        self.ticking = false;
+        self.paused_at_ms = None;
+    }
+
+    /// True while the time is up and the alarm blinks and sounds.
+    pub fn is_alarm_active(&self, current_time_ms: u128) -> bool {
+        self.is_time_up(current_time_ms)
+            && current_time_ms < self.target_time_ms + self.alarm_profile.duration_ms
+    }
+
+    pub fn select_alarm_profile(&mut self, name: &str) -> bool {
+        match self.alarm_profiles.iter().find(|profile| profile.name == name) {
+            Some(profile) => {
+                self.alarm_profile = profile.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Changes a user defined alarm profile, an unknown name adds one based
+    /// on the default profile. Returns false for built-in profiles.
+    pub fn update_alarm_profile<F>(&mut self, name: &str, update: F) -> bool
+    where
+        F: FnOnce(&mut AlarmProfile),
+    {
+        if AlarmProfile::is_built_in(name) {
+            return false;
+        }
+        let index = match self.alarm_profiles.iter().position(|profile| profile.name == name) {
+            Some(index) => index,
+            None => {
+                let mut profile = AlarmProfile::default_profile();
+                profile.name = name.to_string();
+                self.alarm_profiles.push(profile);
+                self.alarm_profiles.len() - 1
+            }
+        };
+        update(&mut self.alarm_profiles[index]);
+        if self.alarm_profile.name == name {
+            self.alarm_profile = self.alarm_profiles[index].clone();
+        }
+        true
+    }
+
+    /// Removes a user defined alarm profile, the presets using it and the
+    /// selection fall back to the default profile.
+    pub fn delete_alarm_profile(&mut self, name: &str) -> bool {
+        if AlarmProfile::is_built_in(name) {
+            return false;
+        }
+        let profile_count = self.alarm_profiles.len();
+        self.alarm_profiles.retain(|profile| profile.name != name);
+        if self.alarm_profiles.len() == profile_count {
+            return false;
+        }
+        for preset in self.presets.iter_mut() {
+            if preset.alarm_profile.as_deref() == Some(name) {
+                preset.alarm_profile = None;
+            }
+        }
+        if self.alarm_profile.name == name {
+            self.alarm_profile = AlarmProfile::default_profile();
+        }
+        true
+    }
+
+    /// Silences an active alarm and ends the timer.
+    pub fn acknowledge_alarm(&mut self, current_time_ms: u128) -> bool {
+        if !self.is_alarm_active(current_time_ms) {
+            return false;
+        }
+        self.last_alarm = Some(AlarmRecord {
+            expired_at_ms: self.target_time_ms,
+            acknowledged_at_ms: Some(current_time_ms),
+            snoozed: false,
+        });
+        self.stop(current_time_ms);
+        self.events.push(HourglassEvent::AlarmAcknowledged);
+        true
+    }
+
+    /// Silences an active alarm and starts a short timer of SNOOZE_DURATION_MS.
+    pub fn snooze(&mut self, current_time_ms: u128) -> bool {
+        if !self.is_alarm_active(current_time_ms) {
+            return false;
         }
+        self.last_alarm = Some(AlarmRecord {
+            expired_at_ms: self.target_time_ms,
+            acknowledged_at_ms: Some(current_time_ms),
+            snoozed: true,
+        });
+        self.duration_ms = SNOOZE_DURATION_MS;
+        self.target_time_ms = current_time_ms + SNOOZE_DURATION_MS;
+        // A pause during the alarm would freeze the snooze.
+        self.paused_at_ms = None;
+        self.events.push(HourglassEvent::Snoozed);
+        true
+    }
+
+    /// Records an alarm that ran out without being acknowledged.
+    pub fn expire_alarm(&mut self, current_time_ms: u128) {
+        self.last_alarm = Some(AlarmRecord {
+            expired_at_ms: self.target_time_ms,
+            acknowledged_at_ms: None,
+            snoozed: false,
+        });
+        self.stop(current_time_ms);
     }
 }
 
 pub type ThreadSafeHourglassState = Arc<RwLock<HourglassState>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alarm::DEFAULT_ALARM_PROFILE;
+    use crate::program::Segment;
+
+    fn get_new_running_state(target_time_ms: u128) -> HourglassState {
+        let mut state = HourglassState::new();
+        state.ticking = true;
+        state.target_time_ms = target_time_ms;
+        state.duration_ms = target_time_ms;
+        state
+    }
+
+    #[test]
+    fn test_pause_freezes_the_remaining_time() {
+        let mut state = get_new_running_state(10000);
+
+        state.pause(4000);
+
+        assert!(!state.is_running());
+        assert_eq!(state.remaining_ms(4000), 6000);
+        assert_eq!(state.remaining_ms(9000), 6000);
+    }
+
+    #[test]
+    fn test_resume_moves_the_target_time() {
+        let mut state = get_new_running_state(10000);
+
+        state.pause(4000);
+        state.resume(7000);
+
+        assert!(state.is_running());
+        assert_eq!(state.target_time_ms, 13000);
+        assert_eq!(state.remaining_ms(7000), 6000);
+        assert_eq!(
+            state.take_events(),
+            vec![HourglassEvent::Paused, HourglassEvent::Resumed]
+        );
+    }
+
+    #[test]
+    fn test_pause_is_ignored_when_not_running() {
+        let mut state = HourglassState::new();
+
+        state.pause(4000);
+        state.resume(5000);
+
+        assert!(!state.is_paused());
+        assert!(state.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_warnings_fire_when_crossed_by_time() {
+        let mut state = get_new_running_state(400_000);
+        let mut tracker = WarningTracker::new();
+
+        assert!(tracker.check(&state, 99_000).is_empty());
+        assert!(tracker.check(&state, 99_900).is_empty());
+        let warnings = tracker.check(&state, 100_100);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].remaining_s, 300);
+        assert!(tracker.check(&state, 100_300).is_empty());
+
+        state.target_time_ms = 161_000;
+        assert!(tracker.check(&state, 100_500).is_empty());
+        let warnings = tracker.check(&state, 101_100);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].remaining_s, 60);
+    }
+
+    #[test]
+    fn test_warnings_do_not_fire_on_adjustments_and_fire_again_on_recrossing() {
+        let mut state = get_new_running_state(130_000);
+        let mut tracker = WarningTracker::new();
+
+        tracker.check(&state, 60_000);
+        // Minute subtracted, 10s remaining
+        state.target_time_ms -= 60_000;
+        assert!(tracker.check(&state, 60_200).is_empty());
+        // Minute added above the threshold again, then subtracted again
+        state.target_time_ms += 60_000;
+        assert!(tracker.check(&state, 60_400).is_empty());
+        state.target_time_ms -= 60_000;
+        assert!(tracker.check(&state, 60_600).is_empty());
+        // Minute added and crossed again by time passing
+        state.target_time_ms += 60_000;
+        assert!(tracker.check(&state, 69_900).is_empty());
+        assert_eq!(tracker.check(&state, 70_100).len(), 1);
+    }
+
+    #[test]
+    fn test_warnings_do_not_fire_while_paused() {
+        let mut state = get_new_running_state(61_000);
+        let mut tracker = WarningTracker::new();
+
+        tracker.check(&state, 0);
+        state.pause(500);
+        assert!(tracker.check(&state, 5_000).is_empty());
+        state.resume(5_000);
+        assert!(tracker.check(&state, 5_200).is_empty());
+        assert_eq!(tracker.check(&state, 6_100).len(), 1);
+    }
+
+    #[test]
+    fn test_next_warning_is_the_nearest_threshold_ahead() {
+        let mut state = get_new_running_state(400_000);
+
+        assert_eq!(state.next_warning_ms(0), Some(100_000));
+        assert_eq!(state.next_warning_ms(100_000), Some(340_000));
+        assert_eq!(state.next_warning_ms(340_000), None);
+
+        state.pause(0);
+        assert_eq!(state.next_warning_ms(0), None);
+    }
+
+    #[test]
+    fn test_parse_and_format_warnings() {
+        let warnings = parse_warnings("300,60:one_minute_warning").unwrap();
+
+        assert_eq!(warnings, default_warnings());
+        assert_eq!(format_warnings(&warnings), "300:warning,60:one_minute_warning");
+        assert_eq!(parse_warnings("none").unwrap(), vec![]);
+        assert_eq!(format_warnings(&[]), "none");
+        assert!(parse_warnings("60:unknown").is_err());
+        assert!(parse_warnings("abc").is_err());
+    }
+
+    #[test]
+    fn test_warnings_do_not_fire_when_starting_at_the_threshold() {
+        let mut state = HourglassState::new();
+        let mut tracker = WarningTracker::new();
+
+        tracker.check(&state, 1_000);
+        state.ticking = true;
+        state.duration_ms = 60_000;
+        state.target_time_ms = 1_100 + 60_000;
+        assert!(tracker.check(&state, 1_250).is_empty());
+        assert!(tracker.check(&state, 1_500).is_empty());
+    }
+
+    #[test]
+    fn test_acknowledge_alarm_stops_the_timer() {
+        let mut state = get_new_running_state(10_000);
+
+        assert!(!state.acknowledge_alarm(9_000));
+        assert!(state.is_alarm_active(12_000));
+        assert!(state.acknowledge_alarm(12_000));
+
+        assert!(!state.ticking);
+        assert!(!state.is_alarm_active(12_000));
+        assert_eq!(
+            state.last_alarm,
+            Some(AlarmRecord {
+                expired_at_ms: 10_000,
+                acknowledged_at_ms: Some(12_000),
+                snoozed: false,
+            })
+        );
+        assert_eq!(state.take_events(), vec![HourglassEvent::AlarmAcknowledged]);
+    }
+
+    #[test]
+    fn test_snooze_rearms_a_short_timer() {
+        let mut state = get_new_running_state(10_000);
+
+        assert!(state.snooze(12_000));
+
+        assert!(state.is_running());
+        assert_eq!(state.remaining_ms(12_000), SNOOZE_DURATION_MS);
+        assert!(state.last_alarm.unwrap().snoozed);
+        assert_eq!(state.take_events(), vec![HourglassEvent::Snoozed]);
+        assert!(!state.snooze(13_000));
+    }
+
+    #[test]
+    fn test_snooze_runs_after_a_pause_during_the_alarm() {
+        let mut state = get_new_running_state(10_000);
+        state.pause(11_000);
+
+        assert!(state.snooze(12_000));
+
+        assert!(state.is_running());
+        assert_eq!(state.remaining_ms(12_000 + SNOOZE_DURATION_MS - 1_000), 1_000);
+        assert!(state.is_time_up(12_000 + SNOOZE_DURATION_MS));
+    }
+
+    #[test]
+    fn test_alarm_profile_sets_the_alarm_duration() {
+        let mut state = get_new_running_state(10_000);
+
+        assert!(state.select_alarm_profile("gentle"));
+        assert!(!state.select_alarm_profile("unknown"));
+
+        assert_eq!(state.alarm_profile.name, "gentle");
+        assert!(state.is_alarm_active(10_000 + 59_999));
+        assert!(!state.is_alarm_active(10_000 + 60_000));
+    }
+
+    #[test]
+    fn test_user_alarm_profiles_are_added_changed_and_deleted() {
+        let mut state = HourglassState::new();
+        assert!(!state.update_alarm_profile("gentle", |profile| profile.duration_ms = 1));
+        assert!(!state.delete_alarm_profile("default"));
+
+        assert!(state.update_alarm_profile("night", |profile| profile.sound = None));
+        assert!(state.select_alarm_profile("night"));
+        assert!(state.update_alarm_profile("night", |profile| profile.duration_ms = 5000));
+        assert_eq!(state.alarm_profile.duration_ms, 5000);
+        assert_eq!(state.alarm_profile.sound, None);
+
+        let mut preset = Preset::new("Nap", 20 * 60 * 1000);
+        preset.alarm_profile = Some("night".to_string());
+        state.presets.push(preset);
+        assert!(state.delete_alarm_profile("night"));
+        assert!(!state.delete_alarm_profile("night"));
+        assert_eq!(state.alarm_profile.name, DEFAULT_ALARM_PROFILE);
+        assert_eq!(state.presets.last().unwrap().alarm_profile, None);
+    }
+
+    #[test]
+    fn test_start_preset_applies_face_and_alarm_profile() {
+        let mut state = HourglassState::new();
+        let mut preset = Preset::new("Reading", 15 * 60 * 1000);
+        preset.clock_face = Some(ClockFace::Digits);
+        preset.alarm_profile = Some("gentle".to_string());
+        state.presets.push(preset);
+
+        assert!(!state.start_preset("unknown", 1_000));
+        assert!(state.start_preset("Reading", 1_000));
+
+        assert!(state.is_running());
+        assert_eq!(state.remaining_ms(1_000), 15 * 60 * 1000);
+        assert_eq!(state.clock_face, ClockFace::Digits);
+        assert_eq!(state.alarm_profile.name, "gentle");
+        assert_eq!(state.take_events(), vec![HourglassEvent::Started]);
+    }
+
+    #[test]
+    fn test_schedule_does_not_interrupt_a_running_timer() {
+        use crate::schedule::{parse_time, parse_weekdays};
+        use chrono::NaiveDate;
+        // 2024-01-01 is a Monday.
+        let local_time = |day: u32, minute: u32| {
+            NaiveDate::from_ymd_opt(2024, 1, day)
+                .unwrap()
+                .and_hms_opt(18, minute, 0)
+                .unwrap()
+        };
+        let mut state = HourglassState::new();
+        state.schedules.push(ScheduleRule::new(
+            "Evening",
+            parse_weekdays("daily").unwrap(),
+            parse_time("18:30").unwrap(),
+            "Teeth",
+            local_time(1, 0),
+        ));
+        state.start_preset("Homework", 1_000);
+
+        assert!(state.run_schedules(local_time(1, 30), 2_000));
+        assert_eq!(state.duration_ms, 45 * 60 * 1000);
+
+        state.stop(3_000);
+        assert!(!state.run_schedules(local_time(1, 31), 4_000));
+        assert!(state.run_schedules(local_time(2, 30), 5_000));
+        assert_eq!(state.duration_ms, 2 * 60 * 1000);
+        assert!(state.is_running());
+    }
+
+    #[test]
+    fn test_program_segments_follow_each_other() {
+        let mut state = HourglassState::new();
+        let work_ms = 25 * 60 * 1000;
+        let break_ms = 5 * 60 * 1000;
+
+        assert!(state.start_program("Pomodoro", 1_000));
+        state.take_events();
+        state.advance_program(1_000 + work_ms - 1);
+        assert!(state.take_events().is_empty());
+
+        // The break starts where the work ended, even if checked late.
+        state.advance_program(1_000 + work_ms + 200);
+        assert_eq!(
+            state.take_events(),
+            vec![HourglassEvent::SegmentStarted(Some(SoundId::OneMinuteWarning))]
+        );
+        assert_eq!(state.target_time_ms, 1_000 + work_ms + break_ms);
+        assert_eq!(state.program.as_ref().unwrap().step, 1);
+    }
+
+    #[test]
+    fn test_program_ends_with_the_alarm_of_the_last_segment() {
+        let mut state = HourglassState::new();
+        state.programs = vec![Program {
+            name: "Short".to_string(),
+            cycle: vec![Segment::new("Go", 1_000)],
+            repetitions: 2,
+            finale: vec![],
+        }];
+
+        assert!(state.start_program("Short", 0));
+        state.advance_program(5_000);
+
+        assert_eq!(state.program.as_ref().unwrap().step, 1);
+        assert_eq!(state.target_time_ms, 2_000);
+        assert!(state.is_alarm_active(5_000));
+        state.acknowledge_alarm(5_000);
+        assert_eq!(state.program, None);
+    }
+
+    #[test]
+    fn test_stopwatch_shares_pause_and_resume() {
+        let mut state = HourglassState::new();
+
+        state.start_stopwatch(1_000);
+        assert_eq!(state.elapsed_ms(4_000), 3_000);
+        state.pause(4_000);
+        assert_eq!(state.elapsed_ms(9_000), 3_000);
+        assert_eq!(state.next_clock_change_ms(9_000), None);
+        assert_eq!(state.lap(9_000), Some(3_000));
+        state.resume(10_000);
+        assert_eq!(state.elapsed_ms(12_000), 5_000);
+        assert_eq!(state.next_clock_change_ms(12_000), Some(13_000));
+        assert_eq!(state.lap(12_000), Some(5_000));
+
+        assert_eq!(state.laps, vec![3_000, 5_000]);
+        assert!(!state.is_time_up(100_000_000));
+        assert!(!state.is_alarm_active(100_000_000));
+        assert_eq!(state.clock_seconds(7_000 + 2 * 3600 * 1000 + 61_000), 61);
+        assert_eq!(state.clock_span_seconds(), 3600);
+    }
+
+    #[test]
+    fn test_tick_stops_during_quiet_hours() {
+        let mut state = get_new_running_state(10_000);
+        state.tick_settings.enabled = true;
+
+        assert_eq!(state.wanted_ticking(4_000, false), Some((10_000, state.tick_settings)));
+        assert_eq!(state.wanted_ticking(4_000, true), None);
+        // It starts again once quiet hours end.
+        assert_eq!(state.wanted_ticking(5_000, false), Some((10_000, state.tick_settings)));
+        assert_eq!(state.wanted_ticking(10_000, false), None);
+    }
+
+    #[test]
+    fn test_minute_added_while_paused_stays_paused() {
+        let mut state = get_new_running_state(10_000);
+        state.tick_settings.enabled = true;
+        state.pause(4_000);
+        state.take_events();
+
+        assert!(state.add_minute(5_000));
+
+        assert!(state.is_paused() && !state.is_running());
+        assert_eq!(state.duration_ms, 70_000);
+        assert_eq!(state.remaining_ms(9_000), 66_000);
+        assert_eq!(state.wanted_ticking(9_000, false), None);
+        assert_eq!(state.take_events(), vec![HourglassEvent::MinuteAdded]);
+        state.resume(10_000);
+        assert_eq!(state.remaining_ms(10_000), 66_000);
+    }
+
+    #[test]
+    fn test_minute_added_while_running_keeps_the_elapsed_time() {
+        let mut state = get_new_running_state(10_000);
+
+        assert!(state.add_minute(4_000));
+
+        assert!(state.is_running());
+        assert_eq!(state.duration_ms, 70_000);
+        assert_eq!(state.remaining_ms(4_000), 66_000);
+        assert_eq!(state.take_events(), vec![HourglassEvent::MinuteAdded]);
+
+        // The alarm rings, the minute counts from now.
+        assert!(state.add_minute(80_000));
+        assert_eq!(state.remaining_ms(80_000), 60_000);
+    }
+
+    #[test]
+    fn test_stopwatch_has_no_minutes_to_change() {
+        let mut state = HourglassState::new();
+        state.start_stopwatch(1_000);
+        state.lap(2_000);
+        state.take_events();
+
+        assert!(!state.add_minute(3_000));
+        assert!(!state.subtract_minute());
+
+        assert_eq!(state.mode, TimerMode::Stopwatch);
+        assert_eq!(state.elapsed_ms(4_000), 3_000);
+        assert_eq!(state.laps, vec![1_000]);
+        assert!(state.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_lap_needs_a_stopwatch() {
+        let mut state = get_new_running_state(10_000);
+
+        assert_eq!(state.lap(5_000), None);
+        assert_eq!(state.elapsed_ms(4_000), 4_000);
+        assert_eq!(state.clock_seconds(4_000), 6);
+        assert_eq!(state.clock_span_seconds(), 10);
+        // The 6 turns into a 5 once less than 6 seconds remain.
+        assert_eq!(state.next_clock_change_ms(4_000), Some(4_001));
+        assert_eq!(state.next_clock_change_ms(4_300), Some(5_001));
+    }
+
+    #[test]
+    fn test_alarm_expires_unacknowledged() {
+        let mut state = get_new_running_state(10_000);
+        let alarm_end_ms = 10_000 + state.alarm_profile.duration_ms;
+
+        assert!(state.is_alarm_active(alarm_end_ms - 1));
+        assert!(!state.is_alarm_active(alarm_end_ms));
+        state.expire_alarm(alarm_end_ms);
+
+        assert!(!state.ticking);
+        assert_eq!(state.last_alarm.unwrap().acknowledged_at_ms, None);
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+use crate::quiet_hours::NightDisplay;
+
+/// Time between two frames of the screensaver, the pace of the main loop.
+pub const SCREENSAVER_FRAME_MS: u128 = 250;
+
+/// Offsets the welcome screen cycles through, at most a pixel each way.
+const WELCOME_OFFSETS: [(isize, isize); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// Protects the OLED from burning in the idle welcome screen: it is moved
+/// around after shift_after_ms, replaced by a screensaver after
+/// screensaver_after_ms and the panel is turned off after off_after_ms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdleSettings {
+    pub shift_after_ms: u128,
+    pub shift_interval_ms: u128,
+    pub screensaver_after_ms: u128,
+    pub off_after_ms: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleStage {
+    /// The welcome screen, moved by the offset.
+    Welcome(isize, isize),
+    /// The frame of the screensaver animation.
+    Screensaver(u128),
+    Off,
+}
+
+/// What the idle screen shows. The night display of quiet hours replaces
+/// the burn-in protection, only the dimmed welcome screen still needs it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleScreen {
+    Welcome(isize, isize),
+    Screensaver(u128),
+    NightClock,
+    /// The panel sleeps.
+    Off,
+}
+
+impl IdleScreen {
+    pub fn new(night_display: Option<NightDisplay>, idle_stage: IdleStage) -> Self {
+        match (night_display, idle_stage) {
+            (Some(NightDisplay::Off), _) => IdleScreen::Off,
+            (Some(NightDisplay::Clock), _) => IdleScreen::NightClock,
+            (_, IdleStage::Welcome(dx, dy)) => IdleScreen::Welcome(dx, dy),
+            (_, IdleStage::Screensaver(frame)) => IdleScreen::Screensaver(frame),
+            (_, IdleStage::Off) => IdleScreen::Off,
+        }
+    }
+}
+
+impl IdleSettings {
+    pub fn new() -> Self {
+        IdleSettings {
+            shift_after_ms: 5 * 60 * 1000,
+            shift_interval_ms: 60 * 1000,
+            screensaver_after_ms: 30 * 60 * 1000,
+            off_after_ms: 2 * 60 * 60 * 1000,
+        }
+    }
+
+    pub fn stage_at(&self, idle_ms: u128) -> IdleStage {
+        if idle_ms >= self.off_after_ms {
+            IdleStage::Off
+        } else if idle_ms >= self.screensaver_after_ms {
+            IdleStage::Screensaver((idle_ms - self.screensaver_after_ms) / SCREENSAVER_FRAME_MS)
+        } else if idle_ms >= self.shift_after_ms {
+            let shift = (idle_ms - self.shift_after_ms) / self.shift_interval_ms.max(1);
+            let (dx, dy) = WELCOME_OFFSETS[shift as usize % WELCOME_OFFSETS.len()];
+            IdleStage::Welcome(dx, dy)
+        } else {
+            IdleStage::Welcome(0, 0)
+        }
+    }
+
+    /// The idle time of the next stage change, None once the panel is off.
+    pub fn next_change_ms(&self, idle_ms: u128) -> Option<u128> {
+        if idle_ms >= self.off_after_ms {
+            None
+        } else if idle_ms >= self.screensaver_after_ms {
+            let frame_ms = idle_ms - self.screensaver_after_ms;
+            let next_frame_ms = (frame_ms / SCREENSAVER_FRAME_MS + 1) * SCREENSAVER_FRAME_MS;
+            Some((self.screensaver_after_ms + next_frame_ms).min(self.off_after_ms))
+        } else if idle_ms >= self.shift_after_ms {
+            let interval_ms = self.shift_interval_ms.max(1);
+            let next_shift_ms = ((idle_ms - self.shift_after_ms) / interval_ms + 1) * interval_ms;
+            Some((self.shift_after_ms + next_shift_ms).min(self.screensaver_after_ms))
+        } else {
+            Some(self.shift_after_ms)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_stages() {
+        let settings = IdleSettings::new();
+        let minute_ms = 60 * 1000;
+
+        assert_eq!(settings.stage_at(0), IdleStage::Welcome(0, 0));
+        assert_eq!(settings.stage_at(5 * minute_ms), IdleStage::Welcome(1, 0));
+        assert_eq!(settings.stage_at(6 * minute_ms), IdleStage::Welcome(1, 1));
+        assert_eq!(settings.stage_at(13 * minute_ms), IdleStage::Welcome(1, 0));
+        assert_eq!(
+            settings.stage_at(30 * minute_ms + 1000),
+            IdleStage::Screensaver(4)
+        );
+        assert_eq!(settings.stage_at(120 * minute_ms), IdleStage::Off);
+    }
+
+    #[test]
+    fn test_night_display_replaces_the_idle_stages() {
+        let settings = IdleSettings::new();
+        let idle_stage = settings.stage_at(settings.off_after_ms + 60 * 1000);
+
+        assert_eq!(
+            IdleScreen::new(Some(NightDisplay::Clock), idle_stage),
+            IdleScreen::NightClock
+        );
+        assert_eq!(
+            IdleScreen::new(Some(NightDisplay::Off), IdleStage::Welcome(0, 0)),
+            IdleScreen::Off
+        );
+        assert_eq!(
+            IdleScreen::new(Some(NightDisplay::Dim), idle_stage),
+            IdleScreen::Off
+        );
+        assert_eq!(
+            IdleScreen::new(None, IdleStage::Screensaver(3)),
+            IdleScreen::Screensaver(3)
+        );
+    }
+
+    #[test]
+    fn test_next_stage_changes() {
+        let settings = IdleSettings::new();
+        let minute_ms = 60 * 1000;
+
+        assert_eq!(settings.next_change_ms(0), Some(5 * minute_ms));
+        assert_eq!(
+            settings.next_change_ms(5 * minute_ms + 10),
+            Some(6 * minute_ms)
+        );
+        assert_eq!(
+            settings.next_change_ms(29 * minute_ms + 10),
+            Some(30 * minute_ms)
+        );
+        assert_eq!(
+            settings.next_change_ms(30 * minute_ms + 10),
+            Some(30 * minute_ms + SCREENSAVER_FRAME_MS)
+        );
+        assert_eq!(settings.next_change_ms(120 * minute_ms), None);
+    }
+}
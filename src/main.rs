@@ -2,127 +2,540 @@
 #![allow(unused_variables)]
 #![allow(unused_imports)]
 
-use audio::wav_player;
-use audio::Player;
+use audio::sound_bank::SoundId;
+use audio::sound_player::SoundPlayer;
+use audio::engine::DEFAULT_VOLUME;
+use audio::{PlayOptions, Player, Ticking};
 
 #[cfg(not(target_arch = "arm"))]
 use crate::gui::display_minifb::MiniFbDisplay;
-#[cfg(target_arch = "arm")]
-use crate::gui::display_raspberry::RaspberryDisplay;
 
-use crate::gui::display_control::{Color, DisplayControl, Point};
-use crate::hourglass::HourglassState;
+use crate::alarm::AlarmOutput;
+use crate::config::{save_config, Config, CONFIG_PATH};
+#[cfg(target_arch = "arm")]
+use crate::control::button::Button;
+use crate::control::button::ButtonPress;
+use crate::control::input::{ConsoleCommand, CONSOLE_HELP};
+use crate::gui::clock_face::ClockFace;
+use crate::gui::compositor::{Compositor, LayerMode};
+use crate::gui::render_scheduler::{RenderScheduler, Tween};
+use crate::gui::display_control::{
+    Brightness, Color, DisplayControl, Orientation, Point, DEFAULT_CONTRAST,
+};
+use crate::gui::warning_cue::{draw_warning_cue, BLINK_INTERVAL_MS, WARNING_CUE_MS};
+use crate::idle::IdleScreen;
+use crate::quiet_hours::NightDisplay;
+use crate::schedule::next_schedule_ms;
+use crate::hourglass::{
+    now_ms, HourglassEvent, HourglassState, TickSettings, TimerMode, WarningTracker,
+};
 
+use chrono::Timelike;
 use std::time::{SystemTime, Duration};
 use std::{sync::Arc, sync::RwLock, thread, time};
 
+mod alarm;
+mod ambient;
 mod audio;
+mod config;
 mod control;
 mod data;
 mod gui;
 mod hourglass;
+mod idle;
+mod program;
+mod quiet_hours;
+mod schedule;
 
-const MAX_BLINK_TIME_MS: u128 = 120000;
+const GOODBYE_TIMEOUT_MS: u64 = 3000;
+const PRESET_CARD_MS: u128 = 3000;
+const CLOCK_TRANSITION_MS: u128 = 600;
+/// While sounds play or load, the audio engine is updated this often.
+const AUDIO_UPDATE_MS: u128 = 250;
 
 #[actix_web::main]
 async fn main() {
-    let wav_file_path = "./audio/424244__aceinet__number-90-flange-the-hammer-on-e.wav".to_string();
-    let mut wav_player = wav_player::WavPlayer::new(wav_file_path);
+    let sound_directory = "./audio/".to_string();
+    let mut player = SoundPlayer::new(sound_directory);
 
-    let hourglass_state = Arc::new(RwLock::new(HourglassState::new()));
-    control::webservice::start_webservice(hourglass_state.clone());
-    let (await_input_enter_thread, await_input_enter_rx) =
-        control::input::spawn_await_input_enter_thread();
+    let mut initial_state = HourglassState::new();
+    Config::load(CONFIG_PATH).apply_to(&mut initial_state);
+    initial_state.touch(now_ms());
+    let hourglass_state = Arc::new(RwLock::new(initial_state));
+    let mut render_scheduler = RenderScheduler::new(hourglass_state.read().unwrap().render.max_fps);
+    control::webservice::start_webservice(hourglass_state.clone(), render_scheduler.waker());
+    let (console_input_thread, console_input_rx) =
+        control::input::spawn_console_input_thread(render_scheduler.waker());
+    #[cfg(target_arch = "arm")]
+    let mut button = Button::new(render_scheduler.waker());
 
+    let display_model = hourglass_state.read().unwrap().display_model;
     #[cfg(target_arch = "arm")]
-    let mut display = RaspberryDisplay::new();
+    let mut display = display_model.create_display();
     #[cfg(not(target_arch = "arm"))]
-    let mut display = MiniFbDisplay::new();
+    let (panel_width, panel_height) = display_model.panel_size();
+    #[cfg(not(target_arch = "arm"))]
+    let mut display = MiniFbDisplay::new(panel_width, panel_height);
 
     thread::sleep(time::Duration::from_millis(1250));
     display.init();
 
-    println!("Hourglass running. Press Enter to end...");
+    println!("Hourglass running. {}", CONSOLE_HELP);
 
     // These variables help minimize the display update.
     // They make the ui drawing look a bit more complex,
     // but save a lot of processing and energy.
-    let mut last_remaining_seconds = 0;
+    let mut last_clock_frame: Option<(ClockFace, u128, Option<u128>)> = None;
+    // Added or subtracted minutes slide the shown seconds to the new time.
+    let mut shown_clock_seconds: Option<u128> = None;
+    let mut clock_tween: Option<Tween> = None;
+    // The clock face is drawn into the base, the banner and the warning cue
+    // are overlays on top of it.
+    let mut compositor = Compositor::new(display.fb());
+    let banner_layer = compositor.add_layer(LayerMode::Copy);
+    let warning_layer = compositor.add_layer(LayerMode::Xor);
     let mut welcome_screen_shown = false;
-    let mut is_filled_white = false;
-    let mut end_audio_played = false;
+    let mut alarm_output = AlarmOutput::new();
+    let mut active_ticking: Option<(u128, TickSettings)> = None;
+    let mut warning_tracker = WarningTracker::new();
+    let mut warning_cue_started_ms: Option<u128> = None;
+    let mut selected_preset: Option<usize> = None;
+    let mut preset_card_started_ms: Option<u128> = None;
+    let mut applied_volume: Option<f32> = None;
+    let mut applied_brightness: Option<Brightness> = None;
+    let mut applied_inverted: Option<bool> = None;
+    let mut applied_orientation: Option<Orientation> = None;
+    let mut last_night_display: Option<NightDisplay> = None;
+    let mut last_night_clock_minute: Option<u32> = None;
+    let mut display_sleeping = false;
+    let mut last_idle_screen: Option<IdleScreen> = None;
 
     loop {
+        let console_command = console_input_rx.try_recv().ok();
+        if console_command == Some(ConsoleCommand::Quit) {
+            println!("Thanks for using hourglass. Good bye!");
+            break;
+        }
+
+        // A short button press acknowledges the alarm, a long one snoozes it.
+        // While idle a short press selects the next preset, a long one starts it.
+        #[cfg(target_arch = "arm")]
+        let button_press = button.poll(now_ms());
+        #[cfg(target_arch = "arm")]
+        if let Some(long_press_ms) = button.long_press_due_ms() {
+            render_scheduler.wake_at(long_press_ms);
+        }
+        #[cfg(not(target_arch = "arm"))]
+        let button_press: Option<ButtonPress> = None;
+        if console_command.is_some() || button_press.is_some() {
+            hourglass_state.write().unwrap().touch(now_ms());
+        }
+
+        let is_idle = !hourglass_state.read().unwrap().ticking;
+        match (console_command, button_press) {
+            (Some(ConsoleCommand::ListPresets), _) => {
+                for (index, preset) in hourglass_state.read().unwrap().presets.iter().enumerate() {
+                    println!("{}: {} ({}s)", index + 1, preset.name, preset.duration_ms / 1000);
+                }
+            }
+            (Some(ConsoleCommand::StartPreset(number)), _) => {
+                let mut hourglass_state_unlocked_rw = hourglass_state.write().unwrap();
+                let name = number
+                    .checked_sub(1)
+                    .and_then(|index| hourglass_state_unlocked_rw.presets.get(index))
+                    .map(|preset| preset.name.clone());
+                match name {
+                    Some(name) => {
+                        hourglass_state_unlocked_rw.start_preset(&name, now_ms());
+                    }
+                    None => println!("There is no preset {}.", number),
+                }
+            }
+            (_, Some(ButtonPress::Short)) if is_idle => {
+                let state = hourglass_state.read().unwrap();
+                if !state.presets.is_empty() {
+                    let index =
+                        selected_preset.map_or(0, |index| (index + 1) % state.presets.len());
+                    let preset = &state.presets[index];
+                    let fb = display.fb();
+                    gui::preset_card::draw_preset_card(&preset.name, preset.duration_ms, fb);
+                    display.safe_swap();
+                    selected_preset = Some(index);
+                    preset_card_started_ms = Some(now_ms());
+                    welcome_screen_shown = true;
+                }
+            }
+            (_, Some(ButtonPress::Long)) if is_idle => {
+                let mut hourglass_state_unlocked_rw = hourglass_state.write().unwrap();
+                let name = selected_preset
+                    .and_then(|index| hourglass_state_unlocked_rw.presets.get(index))
+                    .map(|preset| preset.name.clone());
+                if let Some(name) = name {
+                    hourglass_state_unlocked_rw.start_preset(&name, now_ms());
+                }
+            }
+            (Some(ConsoleCommand::AcknowledgeAlarm), _) | (_, Some(ButtonPress::Short)) => {
+                hourglass_state.write().unwrap().acknowledge_alarm(now_ms());
+            }
+            (Some(ConsoleCommand::Snooze), _) | (_, Some(ButtonPress::Long)) => {
+                hourglass_state.write().unwrap().snooze(now_ms());
+            }
+            _ => {}
+        }
+
+        let local_time = chrono::Local::now().naive_local();
         {
-            let hourglass_state_unlocked_r = hourglass_state.read().unwrap();
-            let finished = await_input_enter_rx.try_recv().is_ok();
-            if finished {
-                println!("Thanks for using hourglass. Good bye!");
-                break;
+            let mut hourglass_state_unlocked_rw = hourglass_state.write().unwrap();
+            if hourglass_state_unlocked_rw.run_schedules(local_time, now_ms()) {
+                save_config(&hourglass_state_unlocked_rw);
+            }
+            hourglass_state_unlocked_rw.advance_program(now_ms());
+        }
+        let mut clock_jumped = false;
+        for event in hourglass_state.write().unwrap().take_events() {
+            match event {
+                HourglassEvent::Started => player.play(SoundId::Start, PlayOptions::once()),
+                HourglassEvent::MinuteAdded => {
+                    clock_jumped = true;
+                    player.play(SoundId::MinuteAdded, PlayOptions::once())
+                }
+                HourglassEvent::Lap => player.play(SoundId::MinuteAdded, PlayOptions::once()),
+                HourglassEvent::MinuteSubtracted => clock_jumped = true,
+                HourglassEvent::Paused | HourglassEvent::Resumed => {}
+                HourglassEvent::SegmentStarted(sound) => {
+                    player.play(sound.unwrap_or(SoundId::Start), PlayOptions::once())
+                }
+                HourglassEvent::AlarmAcknowledged | HourglassEvent::Snoozed => {
+                    alarm_output.stop(&mut display, &mut player)
+                }
+                HourglassEvent::Stopped => player.stop_all(),
+            }
+        }
+
+        let current_time_ms = now_ms();
+        let render_settings = hourglass_state.read().unwrap().render;
+        render_scheduler.set_max_fps(render_settings.max_fps);
+        if let (true, true, Some(shown_seconds)) =
+            (clock_jumped, render_settings.transitions, shown_clock_seconds)
+        {
+            let clock_seconds = hourglass_state.read().unwrap().clock_seconds(current_time_ms);
+            let offset = shown_seconds as f64 - clock_seconds as f64;
+            clock_tween = Some(Tween::new(offset, 0.0, current_time_ms, CLOCK_TRANSITION_MS));
+        }
+
+        // The display follows its settings and the ambient schedule. Quiet
+        // hours lower the volume and the contrast, also of a running timer,
+        // and change the idle screen.
+        let (quiet_hours, display_settings) = {
+            let state = hourglass_state.read().unwrap();
+            let display_settings = state.ambient.apply(state.display, local_time.time());
+            (state.quiet_hours, display_settings)
+        };
+        let is_quiet = quiet_hours.is_active(local_time.time());
+        let night_display = quiet_hours.night_display(local_time.time());
+        let wanted_volume = match is_quiet {
+            true => DEFAULT_VOLUME * quiet_hours.volume,
+            false => DEFAULT_VOLUME,
+        };
+        if Some(wanted_volume) != applied_volume && player.is_ready() {
+            player.set_volume(wanted_volume);
+            applied_volume = Some(wanted_volume);
+        }
+        let orientation = hourglass_state.read().unwrap().orientation;
+        if Some(orientation) != applied_orientation {
+            display.set_orientation(orientation);
+            compositor.match_buffer(display.fb());
+            applied_orientation = Some(orientation);
+            last_clock_frame = None;
+            welcome_screen_shown = false;
+        }
+        if Some(display_settings.brightness) != applied_brightness {
+            display.set_brightness(display_settings.brightness);
+            applied_brightness = Some(display_settings.brightness);
+        }
+        if Some(display_settings.inverted) != applied_inverted {
+            display.set_inverted(display_settings.inverted);
+            applied_inverted = Some(display_settings.inverted);
+        }
+        let rest_contrast = if is_quiet {
+            quiet_hours.contrast
+        } else {
+            display_settings.contrast
+        };
+        // The alarm doesn't flash at full contrast during quiet hours.
+        alarm_output.peak_contrast = match is_quiet {
+            true => quiet_hours.contrast,
+            false => u8::MAX,
+        };
+        if rest_contrast != alarm_output.rest_contrast {
+            alarm_output.rest_contrast = rest_contrast;
+            if !alarm_output.is_active() {
+                display.set_contrast(rest_contrast);
             }
         }
+        if night_display != last_night_display {
+            last_night_display = night_display;
+            welcome_screen_shown = false;
+        }
 
-        let current_time_ms = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
+        // The ticks are scheduled by the audio engine, aligned to the
+        // remaining time. They only need to be rescheduled when the target
+        // time or the settings change, e.g. on resume or an added minute, and
+        // stop during quiet hours.
+        let wanted_ticking = hourglass_state
+            .read()
             .unwrap()
-            .as_millis();
+            .wanted_ticking(current_time_ms, is_quiet);
+        if wanted_ticking != active_ticking && player.is_ready() {
+            match wanted_ticking {
+                Some((target_time_ms, tick_settings)) => {
+                    let interval_ms = tick_settings.interval_s as u128 * 1000;
+                    let remaining_ms = target_time_ms.saturating_sub(current_time_ms);
+                    player.start_ticking(Ticking {
+                        interval: Duration::from_millis(interval_ms as u64),
+                        first_tick_in: Duration::from_millis((remaining_ms % interval_ms) as u64),
+                        tick_count: (remaining_ms / interval_ms) as usize,
+                        volume: tick_settings.volume,
+                    });
+                }
+                None => player.stop_ticking(),
+            }
+            active_ticking = wanted_ticking;
+        }
+
+        let warnings = warning_tracker.check(&hourglass_state.read().unwrap(), current_time_ms);
+        for warning in warnings {
+            player.play(warning.sound, PlayOptions::once());
+            warning_cue_started_ms = Some(current_time_ms);
+            render_scheduler.request_redraw();
+        }
+
+        // A running timer keeps the idle screen awake. The night display
+        // replaces the burn-in protection of the welcome screen.
+        let idle_screen = {
+            let mut state = hourglass_state.write().unwrap();
+            if state.ticking {
+                state.touch(current_time_ms);
+            }
+            IdleScreen::new(night_display, state.idle_stage(current_time_ms))
+        };
+
         if hourglass_state.read().unwrap().ticking {
             welcome_screen_shown = false;
+            preset_card_started_ms = None;
             let target_time_ms = hourglass_state.read().unwrap().target_time_ms;
-            if current_time_ms < target_time_ms {
-                is_filled_white = false;
-                end_audio_played = false;
-                // Draw and animate boxes to show remaining time
-                let remaining_seconds = (target_time_ms - current_time_ms) / 1000;
-                if remaining_seconds != last_remaining_seconds {
-                    last_remaining_seconds = remaining_seconds;
-                    display.fb().fill_with_black();
-                    gui::block_clock::draw_block_clock(remaining_seconds, display.fb());
-                    display.safe_swap();
+            let is_time_up = hourglass_state.read().unwrap().is_time_up(current_time_ms);
+            let alarm_profile = hourglass_state.read().unwrap().alarm_profile.clone();
+            if !is_time_up {
+                if alarm_output.is_active() {
+                    alarm_output.stop(&mut display, &mut player);
                 }
-                wav_player.stop();
-            } else if current_time_ms < target_time_ms + MAX_BLINK_TIME_MS {
-                last_remaining_seconds = 0;
-                // Blink the display to signal "time's up"
-                let fill_white = (current_time_ms / 500) % 2 == 0;
-                if fill_white && !is_filled_white {
-                    is_filled_white = true;
-                    display.fb().fill_with_white();
-                    display.safe_swap();
-                } else if !fill_white && is_filled_white {
-                    is_filled_white = false;
-                    display.fb().fill_with_black();
+                // Draw the remaining time of the countdown or the elapsed
+                // time of the stopwatch
+                let (clock_seconds, span_seconds, clock_face) = {
+                    let state = hourglass_state.read().unwrap();
+                    let clock_seconds = state.clock_seconds(current_time_ms);
+                    let (span_seconds, clock_face) = (state.clock_span_seconds(), state.clock_face);
+                    // Wake for the next second only if it changes the face,
+                    // e.g. the pie moves just a few times per duration.
+                    if let Some(change_ms) = state.next_clock_change_ms(current_time_ms) {
+                        let next_frame =
+                            clock_face.frame(state.clock_seconds(change_ms), span_seconds);
+                        if next_frame != clock_face.frame(clock_seconds, span_seconds) {
+                            render_scheduler.wake_at(change_ms);
+                        }
+                    }
+                    (clock_seconds, span_seconds, clock_face)
+                };
+                let offset = match clock_tween {
+                    Some(tween) if !tween.is_done(current_time_ms) => {
+                        render_scheduler.request_redraw();
+                        tween.value(current_time_ms)
+                    }
+                    _ => {
+                        clock_tween = None;
+                        0.0
+                    }
+                };
+                let clock_seconds = (clock_seconds as f64 + offset).round().max(0.0) as u128;
+                // The border of the warning cue blinks on and off.
+                let warning_blinks = warning_cue_started_ms.map(|started_ms| {
+                    let blinks = current_time_ms.saturating_sub(started_ms) / BLINK_INTERVAL_MS;
+                    render_scheduler.wake_at(started_ms + (blinks + 1) * BLINK_INTERVAL_MS);
+                    blinks
+                });
+                let clock_frame = (
+                    clock_face,
+                    clock_face.frame(clock_seconds, span_seconds),
+                    warning_blinks,
+                );
+                if Some(clock_frame) != last_clock_frame {
+                    render_scheduler.request_redraw();
+                }
+                if render_scheduler.is_frame_due(current_time_ms) {
+                    render_scheduler.frame_drawn(current_time_ms);
+                    last_clock_frame = Some(clock_frame);
+                    shown_clock_seconds = Some(clock_seconds);
+                    let program_progress = {
+                        let state = hourglass_state.read().unwrap();
+                        state.program.as_ref().and_then(|run| run.progress())
+                    };
+                    compositor.base.fill_with_black();
+                    clock_face.draw(clock_seconds, span_seconds, &mut compositor.base);
+                    let banner = compositor.layer(banner_layer);
+                    banner.clear();
+                    if let Some(progress) = program_progress {
+                        gui::program_banner::draw_program_banner(
+                            &progress.segment,
+                            progress.repetition,
+                            progress.repetitions,
+                            &mut banner.buffer,
+                        );
+                        banner.clip = Some(gui::program_banner::program_banner_area(
+                            &banner.buffer,
+                        ));
+                        banner.visible = true;
+                    }
+                    let warning = compositor.layer(warning_layer);
+                    warning.clear();
+                    if let Some(started_ms) = warning_cue_started_ms {
+                        let elapsed_ms = current_time_ms.saturating_sub(started_ms);
+                        if elapsed_ms < WARNING_CUE_MS {
+                            draw_warning_cue(elapsed_ms, &mut warning.buffer);
+                            warning.visible = true;
+                        } else {
+                            warning_cue_started_ms = None;
+                        }
+                    }
+                    compositor.compose(display.fb());
                     display.safe_swap();
                 }
-
-                if !end_audio_played {
-                    wav_player.play(Duration::from_secs(120));
-                    end_audio_played = true;
+            } else if current_time_ms < target_time_ms + alarm_profile.duration_ms {
+                last_clock_frame = None;
+                shown_clock_seconds = None;
+                warning_cue_started_ms = None;
+                // Signal "time's up" as configured by the alarm profile
+                let elapsed_ms = current_time_ms - target_time_ms;
+                alarm_output.update(&alarm_profile, elapsed_ms, &mut display, &mut player);
+                if let Some(change_ms) = alarm_profile.next_change_ms(elapsed_ms) {
+                    render_scheduler.wake_at(target_time_ms + change_ms);
                 }
             } else {
-                let mut hourglass_state_unlocked_rw = hourglass_state.write().unwrap();
-                hourglass_state_unlocked_rw.target_time_ms = SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis();
-                hourglass_state_unlocked_rw.duration_ms = 0;
-                hourglass_state_unlocked_rw.ticking = false;
-                end_audio_played = false;
+                hourglass_state.write().unwrap().expire_alarm(current_time_ms);
+                alarm_output.stop(&mut display, &mut player);
             }
         } else {
-            // Show welcome screen
+            // Show welcome screen, once the preset card was shown long enough
+            if preset_card_started_ms
+                .is_some_and(|started_ms| current_time_ms.saturating_sub(started_ms) >= PRESET_CARD_MS)
+            {
+                preset_card_started_ms = None;
+                selected_preset = None;
+                welcome_screen_shown = false;
+            }
+            let night_clock_minute = (night_display == Some(NightDisplay::Clock))
+                .then(|| local_time.hour() * 60 + local_time.minute());
+            if night_clock_minute != last_night_clock_minute {
+                last_night_clock_minute = night_clock_minute;
+                welcome_screen_shown = false;
+            }
+            if Some(idle_screen) != last_idle_screen && preset_card_started_ms.is_none() {
+                last_idle_screen = Some(idle_screen);
+                welcome_screen_shown = false;
+            }
             if !welcome_screen_shown {
                 welcome_screen_shown = true;
-                last_remaining_seconds = 0;
-                is_filled_white = false;
-                display.fb().fill_with_pixmap(&data::WELCOME_SCREEN_PIXMAP);
+                last_clock_frame = None;
+                shown_clock_seconds = None;
+                alarm_output.stop(&mut display, &mut player);
+                match idle_screen {
+                    IdleScreen::Off => display.fb().fill_with_black(),
+                    IdleScreen::NightClock => gui::night_clock::draw_night_clock(
+                        local_time.hour(),
+                        local_time.minute(),
+                        display.fb(),
+                    ),
+                    IdleScreen::Screensaver(frame) => {
+                        gui::screensaver::draw_screensaver(frame, display.fb())
+                    }
+                    IdleScreen::Welcome(dx, dy) => {
+                        display.fb().fill_with_pixmap(&data::WELCOME_SCREEN_PIXMAP);
+                        display.fb().shift(dx, dy);
+                    }
+                }
                 display.safe_swap();
-                wav_player.stop();
             }
         }
-        thread::sleep(time::Duration::from_millis(250));
+
+        // The panel sleeps through idle quiet hours and after a long idle
+        // time, a preset card wakes it.
+        let wanted_sleeping = idle_screen == IdleScreen::Off
+            && !hourglass_state.read().unwrap().ticking
+            && preset_card_started_ms.is_none();
+        if wanted_sleeping != display_sleeping {
+            match wanted_sleeping {
+                true => display.sleep(),
+                false => display.wake(),
+            }
+            display_sleeping = wanted_sleeping;
+        }
+        // The loop sleeps until the countdown ends or crosses a warning, a
+        // schedule is due, quiet hours or an ambient step begin or end, the
+        // idle screen or the night clock changes or the preset card ends.
+        {
+            let state = hourglass_state.read().unwrap();
+            if state.is_running()
+                && state.mode == TimerMode::Countdown
+                && !state.is_time_up(current_time_ms)
+            {
+                render_scheduler.wake_at(state.target_time_ms);
+            }
+            if let Some(warning_ms) = state.next_warning_ms(current_time_ms) {
+                render_scheduler.wake_at(warning_ms);
+            }
+            let time_of_day_changes = [
+                next_schedule_ms(&state.schedules, local_time),
+                quiet_hours.next_change_ms(local_time.time()),
+                state.ambient.next_change_ms(local_time.time()),
+            ];
+            for change_ms in time_of_day_changes.iter().flatten() {
+                render_scheduler.wake_at(current_time_ms + change_ms);
+            }
+            if !state.ticking {
+                let idle_ms = current_time_ms.saturating_sub(state.last_activity_ms);
+                if let Some(change_ms) = state.idle.next_change_ms(idle_ms) {
+                    render_scheduler.wake_at(state.last_activity_ms + change_ms);
+                }
+                if night_display == Some(NightDisplay::Clock) {
+                    let minute_ms = local_time.second() as u128 * 1000
+                        + local_time.nanosecond() as u128 / 1_000_000;
+                    let next_minute_ms = 60_000u128.saturating_sub(minute_ms);
+                    render_scheduler.wake_at(current_time_ms + next_minute_ms);
+                }
+            }
+        }
+        if let Some(started_ms) = preset_card_started_ms {
+            render_scheduler.wake_at(started_ms + PRESET_CARD_MS);
+        }
+
+        player.update();
+        if !player.is_ready() || player.is_playing() {
+            render_scheduler.wake_at(current_time_ms + AUDIO_UPDATE_MS);
+        }
+        // Sleeps until the next frame, deadline, command or button edge.
+        render_scheduler.wait(now_ms());
+    }
+
+    player.stop_all();
+    player.play(SoundId::Goodbye, PlayOptions::once());
+    let goodbye_start = time::Instant::now();
+    while player.is_playing()
+        && goodbye_start.elapsed() < time::Duration::from_millis(GOODBYE_TIMEOUT_MS)
+    {
+        player.update();
+        thread::sleep(time::Duration::from_millis(50));
     }
 
     display.deinit();
-    await_input_enter_thread.join().unwrap();
+    console_input_thread.join().unwrap();
 }
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::sound_bank::SoundId;
+use crate::gui::clock_face::ClockFace;
+
+/// One countdown of a program, e.g. 25 minutes of work.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub label: String,
+    pub duration_ms: u128,
+    #[serde(default)]
+    pub clock_face: Option<ClockFace>,
+    /// Played when the segment starts, the start sound if not set.
+    #[serde(default)]
+    pub transition_sound: Option<SoundId>,
+}
+
+impl Segment {
+    pub fn new(label: &str, duration_ms: u128) -> Self {
+        Segment {
+            label: label.to_string(),
+            duration_ms,
+            clock_face: None,
+            transition_sound: None,
+        }
+    }
+}
+
+/// Segments run one after another: the cycle is repeated and followed by
+/// the finale, the alarm only sounds when the last segment ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub name: String,
+    pub cycle: Vec<Segment>,
+    pub repetitions: u32,
+    #[serde(default)]
+    pub finale: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step<'a> {
+    pub segment: &'a Segment,
+    pub repetition: u32,
+}
+
+impl Program {
+    pub fn pomodoro() -> Self {
+        let mut work = Segment::new("Work", 25 * 60 * 1000);
+        work.transition_sound = Some(SoundId::Start);
+        let mut short_break = Segment::new("Break", 5 * 60 * 1000);
+        short_break.transition_sound = Some(SoundId::OneMinuteWarning);
+        let mut long_break = Segment::new("Long break", 15 * 60 * 1000);
+        long_break.transition_sound = Some(SoundId::OneMinuteWarning);
+        Program {
+            name: "Pomodoro".to_string(),
+            cycle: vec![work, short_break],
+            repetitions: 4,
+            finale: vec![long_break],
+        }
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.cycle.len() * self.repetitions as usize + self.finale.len()
+    }
+
+    /// The step at the index into the flattened program, repetitions count
+    /// from 1 and the finale belongs to the last repetition.
+    pub fn step(&self, index: usize) -> Option<Step<'_>> {
+        let cycle_steps = self.cycle.len() * self.repetitions as usize;
+        if index < cycle_steps {
+            Some(Step {
+                segment: &self.cycle[index % self.cycle.len()],
+                repetition: (index / self.cycle.len()) as u32 + 1,
+            })
+        } else {
+            self.finale.get(index - cycle_steps).map(|segment| Step {
+                segment,
+                repetition: self.repetitions,
+            })
+        }
+    }
+}
+
+/// A running program and the index of its current step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramRun {
+    pub program: Program,
+    pub step: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProgramProgress {
+    pub program: String,
+    pub segment: String,
+    pub step: usize,
+    pub step_count: usize,
+    pub repetition: u32,
+    pub repetitions: u32,
+}
+
+impl ProgramRun {
+    pub fn current(&self) -> Option<Step<'_>> {
+        self.program.step(self.step)
+    }
+
+    pub fn progress(&self) -> Option<ProgramProgress> {
+        let step = self.current()?;
+        Some(ProgramProgress {
+            program: self.program.name.clone(),
+            segment: step.segment.label.clone(),
+            step: self.step + 1,
+            step_count: self.program.step_count(),
+            repetition: step.repetition,
+            repetitions: self.program.repetitions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pomodoro_steps() {
+        let program = Program::pomodoro();
+
+        assert_eq!(program.step_count(), 9);
+        let labels: Vec<&str> = (0..9)
+            .map(|index| program.step(index).unwrap().segment.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Work",
+                "Break",
+                "Work",
+                "Break",
+                "Work",
+                "Break",
+                "Work",
+                "Break",
+                "Long break"
+            ]
+        );
+        assert_eq!(program.step(2).unwrap().repetition, 2);
+        assert_eq!(program.step(8).unwrap().repetition, 4);
+        assert_eq!(program.step(9), None);
+    }
+
+    #[test]
+    fn test_progress_counts_from_one() {
+        let run = ProgramRun {
+            program: Program::pomodoro(),
+            step: 3,
+        };
+
+        let progress = run.progress().unwrap();
+
+        assert_eq!(progress.segment, "Break");
+        assert_eq!(progress.step, 4);
+        assert_eq!(progress.repetition, 2);
+        assert_eq!(progress.repetitions, 4);
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+use crate::schedule::ms_until;
+
+/// What the idle screen shows during quiet hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NightDisplay {
+    /// The panel sleeps until a timer is started.
+    Off,
+    /// The welcome screen at the quiet contrast.
+    Dim,
+    /// A small wall clock at the quiet contrast.
+    Clock,
+}
+
+impl NightDisplay {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NightDisplay::Off => "off",
+            NightDisplay::Dim => "dim",
+            NightDisplay::Clock => "clock",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(NightDisplay::Off),
+            "dim" => Some(NightDisplay::Dim),
+            "clock" => Some(NightDisplay::Clock),
+            _ => None,
+        }
+    }
+}
+
+/// A nightly period from start to end local time with reduced volume and
+/// contrast. A running timer and its alarm still work, only quieter.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Volume of all sounds relative to the normal volume, 0 mutes them.
+    pub volume: f32,
+    pub contrast: u8,
+    pub display: NightDisplay,
+}
+
+impl QuietHours {
+    pub fn new() -> Self {
+        QuietHours {
+            enabled: false,
+            start: NaiveTime::from_hms_opt(20, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            volume: 0.2,
+            contrast: 0x10,
+            display: NightDisplay::Clock,
+        }
+    }
+
+    /// True between start and end, also when the period spans midnight.
+    pub fn is_active(&self, time: NaiveTime) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    /// The display of the idle screen, None outside of quiet hours.
+    pub fn night_display(&self, time: NaiveTime) -> Option<NightDisplay> {
+        self.is_active(time).then_some(self.display)
+    }
+
+    /// Time until quiet hours start or end, None while disabled.
+    pub fn next_change_ms(&self, time: NaiveTime) -> Option<u128> {
+        self.enabled
+            .then(|| ms_until(time, self.start).min(ms_until(time, self.end)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_quiet_hours_span_midnight() {
+        let mut quiet_hours = QuietHours::new();
+        assert!(!quiet_hours.is_active(time(23, 0)));
+
+        quiet_hours.enabled = true;
+
+        assert!(!quiet_hours.is_active(time(19, 59)));
+        assert!(quiet_hours.is_active(time(20, 0)));
+        assert!(quiet_hours.is_active(time(0, 30)));
+        assert!(quiet_hours.is_active(time(6, 59)));
+        assert!(!quiet_hours.is_active(time(7, 0)));
+    }
+
+    #[test]
+    fn test_quiet_hours_within_a_day() {
+        let mut quiet_hours = QuietHours::new();
+        quiet_hours.enabled = true;
+        quiet_hours.start = time(13, 0);
+        quiet_hours.end = time(15, 0);
+
+        assert!(quiet_hours.is_active(time(14, 0)));
+        assert!(!quiet_hours.is_active(time(23, 0)));
+        assert_eq!(quiet_hours.night_display(time(12, 0)), None);
+        assert_eq!(
+            quiet_hours.night_display(time(13, 0)),
+            Some(NightDisplay::Clock)
+        );
+    }
+
+    #[test]
+    fn test_next_change_is_the_nearer_boundary() {
+        let mut quiet_hours = QuietHours::new();
+        assert_eq!(quiet_hours.next_change_ms(time(12, 0)), None);
+
+        quiet_hours.enabled = true;
+        let hour_ms = 60 * 60 * 1000;
+
+        assert_eq!(quiet_hours.next_change_ms(time(19, 0)), Some(hour_ms));
+        assert_eq!(quiet_hours.next_change_ms(time(6, 30)), Some(hour_ms / 2));
+    }
+}
@@ -0,0 +1,235 @@
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// Occurrences missed for longer, e.g. while the device was off, are skipped.
+const CATCH_UP_WINDOW_MIN: i64 = 10;
+
+const ALL_WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Starts a preset on the given weekdays at a local time. The last fired
+/// occurrence is stored with the rule, so it never fires twice, also not
+/// across restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    pub name: String,
+    pub weekdays: Vec<Weekday>,
+    pub time: NaiveTime,
+    pub preset: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub last_fired: Option<NaiveDateTime>,
+}
+
+impl ScheduleRule {
+    /// A new rule only fires for occurrences after its creation.
+    pub fn new(
+        name: &str,
+        weekdays: Vec<Weekday>,
+        time: NaiveTime,
+        preset: &str,
+        now: NaiveDateTime,
+    ) -> Self {
+        ScheduleRule {
+            name: name.to_string(),
+            weekdays,
+            time,
+            preset: preset.to_string(),
+            enabled: true,
+            last_fired: Some(now),
+        }
+    }
+
+    pub fn latest_occurrence(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        (0..=7)
+            .map(|days_back| now.date() - Duration::days(days_back))
+            .filter(|date| self.weekdays.contains(&date.weekday()))
+            .map(|date| date.and_time(self.time))
+            .find(|occurrence| *occurrence <= now)
+    }
+
+    /// The occurrence to fire now, if it is recent and was not fired yet.
+    pub fn due_occurrence(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if !self.enabled {
+            return None;
+        }
+        let occurrence = self.latest_occurrence(now)?;
+        let is_recent = now - occurrence < Duration::minutes(CATCH_UP_WINDOW_MIN);
+        let is_fired = self
+            .last_fired
+            .is_some_and(|last_fired| last_fired >= occurrence);
+        (is_recent && !is_fired).then_some(occurrence)
+    }
+}
+
+/// Time until the next enabled rule's time of day, the loop may sleep until
+/// then. The weekdays are not checked, a wake on another day does no harm.
+pub fn next_schedule_ms(rules: &[ScheduleRule], now: NaiveDateTime) -> Option<u128> {
+    rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .map(|rule| ms_until(now.time(), rule.time))
+        .min()
+}
+
+/// Time from now until the next time the clock shows the time of day, a
+/// full day if it shows it now.
+pub fn ms_until(now: NaiveTime, time: NaiveTime) -> u128 {
+    let day_ms = Duration::days(1).num_milliseconds();
+    let ms = (time - now).num_milliseconds().rem_euclid(day_ms);
+    match ms {
+        0 => day_ms as u128,
+        ms => ms as u128,
+    }
+}
+
+/// Marks the due rules as fired and returns their presets.
+pub fn take_due_presets(rules: &mut [ScheduleRule], now: NaiveDateTime) -> Vec<String> {
+    rules
+        .iter_mut()
+        .filter_map(|rule| {
+            let occurrence = rule.due_occurrence(now)?;
+            rule.last_fired = Some(occurrence);
+            Some(rule.preset.clone())
+        })
+        .collect()
+}
+
+/// Parses "daily", "weekdays", "weekends" or a list like "mon,wed,fri".
+pub fn parse_weekdays(text: &str) -> Result<Vec<Weekday>, String> {
+    match text {
+        "daily" => Ok(ALL_WEEKDAYS.to_vec()),
+        "weekdays" => Ok(ALL_WEEKDAYS[..5].to_vec()),
+        "weekends" => Ok(ALL_WEEKDAYS[5..].to_vec()),
+        _ => text
+            .split(',')
+            .map(|day| Weekday::from_str(day).map_err(|_| format!("Unknown weekday \"{}\".", day)))
+            .collect(),
+    }
+}
+
+/// Parses a local time like "18:30".
+pub fn parse_time(text: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(text, "%H:%M").map_err(|_| format!("Invalid time \"{}\".", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    // 2024-01-01 is a Monday.
+    fn date_time(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn get_new_rule(weekdays: &str) -> ScheduleRule {
+        ScheduleRule::new(
+            "Screen time",
+            parse_weekdays(weekdays).unwrap(),
+            parse_time("18:30").unwrap(),
+            "Screen time",
+            date_time(1, 0, 0),
+        )
+    }
+
+    #[test]
+    fn test_latest_occurrence_skips_other_weekdays() {
+        let rule = get_new_rule("weekdays");
+
+        // Sunday evening, the last occurrence was on Friday.
+        assert_eq!(
+            rule.latest_occurrence(date_time(7, 20, 0)),
+            Some(date_time(5, 18, 30))
+        );
+        assert_eq!(
+            rule.latest_occurrence(date_time(8, 18, 30)),
+            Some(date_time(8, 18, 30))
+        );
+    }
+
+    #[test]
+    fn test_rule_fires_once() {
+        let mut rules = vec![get_new_rule("mon")];
+
+        assert!(take_due_presets(&mut rules, date_time(1, 18, 29)).is_empty());
+        assert_eq!(
+            take_due_presets(&mut rules, date_time(1, 18, 30)),
+            vec!["Screen time".to_string()]
+        );
+        assert!(take_due_presets(&mut rules, date_time(1, 18, 31)).is_empty());
+        assert_eq!(take_due_presets(&mut rules, date_time(8, 18, 32)).len(), 1);
+    }
+
+    #[test]
+    fn test_missed_occurrences_are_skipped() {
+        let mut rules = vec![get_new_rule("daily")];
+
+        // Back on after a restart, only the recent occurrence is caught up.
+        assert!(take_due_presets(&mut rules, date_time(1, 19, 0)).is_empty());
+        assert_eq!(take_due_presets(&mut rules, date_time(2, 18, 35)).len(), 1);
+    }
+
+    #[test]
+    fn test_new_rule_does_not_fire_for_the_past() {
+        let rule = ScheduleRule::new(
+            "Tidy up",
+            parse_weekdays("daily").unwrap(),
+            parse_time("19:00").unwrap(),
+            "Tidy up",
+            date_time(1, 19, 5),
+        );
+
+        assert_eq!(rule.due_occurrence(date_time(1, 19, 6)), None);
+    }
+
+    #[test]
+    fn test_parse_weekdays() {
+        assert_eq!(
+            parse_weekdays("weekends").unwrap(),
+            vec![Weekday::Sat, Weekday::Sun]
+        );
+        assert_eq!(
+            parse_weekdays("mon,Friday").unwrap(),
+            vec![Weekday::Mon, Weekday::Fri]
+        );
+        assert!(parse_weekdays("someday").is_err());
+        assert!(parse_time("25:00").is_err());
+    }
+
+    #[test]
+    fn test_next_schedule_wraps_around_midnight() {
+        let mut rules = vec![get_new_rule("mon")];
+        let minute_ms = 60 * 1000;
+
+        assert_eq!(next_schedule_ms(&rules, date_time(1, 18, 0)), Some(30 * minute_ms));
+        assert_eq!(
+            next_schedule_ms(&rules, date_time(1, 18, 30)),
+            Some(24 * 60 * minute_ms)
+        );
+        assert_eq!(
+            next_schedule_ms(&rules, date_time(1, 19, 0)),
+            Some(23 * 60 * minute_ms + 30 * minute_ms)
+        );
+
+        rules[0].enabled = false;
+        assert_eq!(next_schedule_ms(&rules, date_time(1, 18, 0)), None);
+    }
+}